@@ -4,4 +4,81 @@ connect-joycon = Please connect the Joy-Con to the computer.
 initializing-joycon = Initializing Joy-Con…
 connect-ringcon = Please insert the Joy-Con into the Ring-Con.
 status-flex = Current flex: {$flex}
+status-flex-percent = Flex: {$percent}, sending {$value}
+in-range-percent = {$percent} of the input range
 restarting = Restarting…
+unsupported-joycon-l = A left Joy-Con was connected. The Ring-Con needs the right Joy-Con.
+unsupported-procon = A Pro Controller was connected. The Ring-Con needs the right Joy-Con.
+choose-joycon = Several right Joy-Cons are connected. Please choose the one in the Ring-Con.
+unsupported-device = This controller can't read the Ring-Con. Please connect the right Joy-Con.
+hardware-error = The Joy-Con couldn't be opened. Close other programs which use Joy-Cons, such as Steam or BetterJoy.
+unsupported-firmware = This Joy-Con didn't start its expansion port, so it can't read the Ring-Con. Newer and unofficial Joy-Cons aren't supported.
+battery = Battery: {$percent}%
+battery-charging = Battery: {$percent}% (charging)
+observed = Observed: {$min}–{$max}
+use-observed = Use these
+calibration-needed = The Ring-Con moves past the input range. Use the observed range to recalibrate.
+undo = Undo
+ranges = Input {$input}, output {$output}
+constant-output = The output range is empty, so the output never changes.
+units = Units
+unit-raw = Raw
+unit-percent = Percent
+font = Font
+font-default = Language default
+font-restart = Font changes take effect after restarting.
+ui-scale = Size
+attack = Attack
+release = Release
+curve = Curve
+straight-curve = Straighten
+curve-help = Click to add a point, drag to move it and right click to remove it. Left is the start of the output range.
+mini-mode = Mini mode
+full-mode = Full
+panic-reset = Reset avatar
+low-power = Save power
+auto-start = Start when opened
+not-started = Not reading the Joy-Con yet.
+start = Start
+audio-cues = Sound cues
+copy-config = Copy settings
+paste-config = Paste settings
+paste-failed = The clipboard doesn't contain settings copied from this program.
+osc-address = Address
+apply = Apply
+live-apply = Apply changes right away
+osc-address-suggestion = Did you mean {$suggestion}?
+check-send = Test connection
+firewall-local-sent = Sending to this computer works. Windows Firewall doesn't block this, so its prompt can be cancelled.
+firewall-local-nothing-listening = Nothing is listening on this port. Check that VRChat is running with OSC enabled.
+firewall-remote-sent = Sent to the other computer. If it doesn't arrive, allow this program on private networks when Windows Firewall asks, and check the firewall on the other computer.
+firewall-remote-nothing-listening = The other computer answered, but nothing is listening on this port.
+firewall-blocked = Sending was refused. A firewall rule is probably blocking this program.
+osc-in-unavailable = Can't listen for avatar changes on port {$port}: {$error} Another program may be using the port; set osc_in_port in the configuration file to the port VRChat sends to.
+init-configuring = Configuring the Joy-Con
+init-mcu-state = Starting the expansion port
+init-mcu-configuration = Configuring the expansion port
+init-detecting = Looking for the Ring-Con
+init-configuring-ringcon = Configuring the Ring-Con
+init-polling = Starting the Ring-Con
+init-finishing = Finishing up
+show-log = Show connection log
+hide-log = Hide connection log
+log-not-connected = Waiting for the Joy-Con
+log-initializing = Setting up the Joy-Con
+log-no-ringcon = Ring-Con missing
+log-active = Reading the Ring-Con
+log-disconnected = Joy-Con lost
+log-unsupported-device = Unsupported controller connected
+log-multiple-devices = Several right Joy-Cons connected
+log-hardware-error = Joy-Con couldn't be opened
+log-unsupported-firmware = Joy-Con didn't start its expansion port
+wizard-title = Setup
+wizard-connect = Connect the right Joy-Con to the computer and insert it into the Ring-Con.
+wizard-port = Start VRChat with OSC enabled and press "Find VRChat", then test the connection.
+wizard-address = Sending to {$address}
+wizard-find = Find VRChat
+wizard-calibrate = Squeeze the Ring-Con as far as it goes, then pull it as far as it goes.
+wizard-next = Next
+wizard-finish = Finish
+wizard-skip = Skip setup
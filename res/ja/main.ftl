@@ -1,7 +1,84 @@
-fonts = Meiryo UI
+fonts = Meiryo UI;Yu Gothic UI;MS UI Gothic
 title = OSC Ring-Con
 connect-joycon = パソコンにJoy-Conを接続してください。
 initializing-joycon = Joy-Conを始めています・・・
 connect-ringcon = Ring-ConにJoy-Conを入ってください。
 status-flex = フレックス: {$flex}
+status-flex-percent = フレックス: {$percent}、送信値: {$value}
+in-range-percent = 入力範囲の{$percent}
 restarting = 再起動しています・・・
+unsupported-joycon-l = 左のJoy-Conが接続されました。Ring-Conには右のJoy-Conが必要です。
+unsupported-procon = Proコントローラーが接続されました。Ring-Conには右のJoy-Conが必要です。
+choose-joycon = 右のJoy-Conが複数接続されています。Ring-Conに入っているものを選んでください。
+unsupported-device = このコントローラーではRing-Conを読めません。右のJoy-Conを接続してください。
+hardware-error = Joy-Conを開けませんでした。SteamやBetterJoyなど、Joy-Conを使う他のプログラムを閉じてください。
+unsupported-firmware = このJoy-Conは拡張ポートを起動しなかったため、Ring-Conを読めません。新しいJoy-Conや非公式のJoy-Conには対応していません。
+battery = バッテリー: {$percent}%
+battery-charging = バッテリー: {$percent}%（充電中）
+observed = 観測値: {$min}～{$max}
+use-observed = これを使う
+calibration-needed = Ring-Conが入力範囲を超えています。観測値を使って調整し直してください。
+undo = 元に戻す
+ranges = 入力 {$input}、出力 {$output}
+constant-output = 出力範囲の幅がゼロのため、出力は変わりません。
+units = 単位
+unit-raw = 生の値
+unit-percent = パーセント
+font = フォント
+font-default = 言語の既定
+font-restart = フォントの変更は再起動後に反映されます。
+ui-scale = サイズ
+attack = アタック
+release = リリース
+curve = カーブ
+straight-curve = 直線に戻す
+curve-help = クリックで点を追加、ドラッグで移動、右クリックで削除します。左端が出力範囲の始まりです。
+mini-mode = ミニモード
+full-mode = 通常表示
+panic-reset = アバターをリセット
+low-power = 省電力
+auto-start = 起動時に開始する
+not-started = まだJoy-Conを読んでいません。
+start = 開始
+audio-cues = 効果音
+copy-config = 設定をコピー
+paste-config = 設定を貼り付け
+paste-failed = クリップボードにこのプログラムからコピーした設定がありません。
+osc-address = アドレス
+apply = 適用
+live-apply = 変更をすぐに適用する
+osc-address-suggestion = {$suggestion} ではありませんか？
+check-send = 接続テスト
+firewall-local-sent = このコンピューターへの送信は正常です。Windowsファイアウォールはこれをブロックしないので、確認画面はキャンセルしても大丈夫です。
+firewall-local-nothing-listening = このポートで受信しているプログラムがありません。VRChatが起動していて、OSCが有効になっているか確認してください。
+firewall-remote-sent = 他のコンピューターに送信しました。届かない場合は、Windowsファイアウォールの確認画面でプライベートネットワークを許可し、相手のコンピューターのファイアウォールも確認してください。
+firewall-remote-nothing-listening = 他のコンピューターは応答しましたが、このポートで受信しているプログラムがありません。
+firewall-blocked = 送信が拒否されました。ファイアウォールのルールがこのプログラムをブロックしている可能性があります。
+osc-in-unavailable = ポート{$port}でアバターの変更を受信できません: {$error} 他のプログラムがこのポートを使っている可能性があります。設定ファイルのosc_in_portをVRChatの送信先ポートに設定してください。
+init-configuring = Joy-Conを設定しています
+init-mcu-state = 拡張ポートを起動しています
+init-mcu-configuration = 拡張ポートを設定しています
+init-detecting = Ring-Conを探しています
+init-configuring-ringcon = Ring-Conを設定しています
+init-polling = Ring-Conを起動しています
+init-finishing = 仕上げています
+show-log = 接続履歴を表示
+hide-log = 接続履歴を隠す
+log-not-connected = Joy-Conを待っています
+log-initializing = Joy-Conを設定しています
+log-no-ringcon = Ring-Conが見つかりません
+log-active = Ring-Conを読んでいます
+log-disconnected = Joy-Conが切断されました
+log-unsupported-device = 非対応のコントローラーが接続されました
+log-multiple-devices = 右のJoy-Conが複数接続されました
+log-hardware-error = Joy-Conを開けませんでした
+log-unsupported-firmware = Joy-Conが拡張ポートを起動しませんでした
+wizard-title = セットアップ
+wizard-connect = 右のJoy-Conをパソコンに接続して、Ring-Conに入れてください。
+wizard-port = OSCを有効にしてVRChatを起動し、「VRChatを探す」を押してから接続をテストしてください。
+wizard-address = 送信先: {$address}
+wizard-find = VRChatを探す
+wizard-calibrate = Ring-Conをいっぱいまで押し込んでから、いっぱいまで引っ張ってください。
+wizard-next = 次へ
+wizard-finish = 完了
+wizard-skip = セットアップをスキップ
@@ -1,19 +1,140 @@
-use std::{io::stdin, process::Stdio};
-use tokio::{io::AsyncWriteExt, sync::watch};
+use std::{
+    ffi::OsString,
+    fs,
+    io::stdin,
+    os::windows::io::RawHandle,
+    path::PathBuf,
+    process::Stdio,
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+use tokio::{io::AsyncWriteExt, sync::watch, time::Instant};
 
 use anyhow::{anyhow, Context};
 use futures::{channel::mpsc, StreamExt};
 use ipc_channel::{
     asynch::IpcStream,
-    ipc::{self, IpcSender},
+    ipc::{self, IpcError, IpcReceiver, IpcSender, TryRecvError},
+};
+use osc_ringcon::{
+    joycon::{self, Sink},
+    mapping::MappedOutput,
+    messages::{Configuration, OrphanPolicy, Status},
+    osc::{OscIn, OscOut, AVATAR_PLACEHOLDER},
 };
+use serde::{Deserialize, Serialize};
 
-use crate::{
-    joycon::joycon_main,
-    messages::{Configuration, Status},
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::HANDLE,
+        System::JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+            SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+            JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        },
+    },
 };
 
+use crate::metrics::{self, Metrics};
+
+/// How long the agent may go without reporting while it should be talking to the controller.
+const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(10);
+/// Like [`WATCHDOG_TIMEOUT`], once the controller is initialized and reports
+/// at least every second.
+const ACTIVE_WATCHDOG_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long either side waits for the other while setting up the IPC channels.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Handshake = (IpcSender<Configuration>, IpcReceiver<Report>);
+/// The agent's ends of the channels to a window.
+type Window = (IpcReceiver<Configuration>, IpcSender<Report>);
+/// The agent's ends of the channels to a window which takes over a running
+/// agent, with one through which the window can make it exit.
+type Attach = (
+    IpcReceiver<Configuration>,
+    IpcSender<Report>,
+    IpcReceiver<()>,
+);
+/// The window's ends of the channels to an agent it took over.
+type Attached = (IpcSender<Configuration>, IpcStream<Report>, IpcSender<()>);
+
+/// What the agent tells the window.
+#[derive(Debug, Serialize, Deserialize)]
+enum Report {
+    Status(Status),
+    /// Why the agent couldn't listen on `osc_in_port`, usually because
+    /// another program has it, or `None` once that is sorted out.
+    OscIn(Option<String>),
+}
+
+/// A running agent, which this process either started or took over.
+enum Agent {
+    Child(tokio::process::Child),
+    /// Can't be killed from here, so it is asked to exit instead.
+    Attached(IpcSender<()>),
+}
+
+/// Where a lingering agent writes the name of the server a new window can
+/// attach through.
+fn address_path() -> Option<PathBuf> {
+    let mut path = PathBuf::from(std::env::var_os("APPDATA")?);
+    path.push("osc-ringcon");
+    path.push("agent-address");
+    Some(path)
+}
+
 pub(crate) fn run() -> anyhow::Result<()> {
+    // Reading stdin can't be interrupted, so wait for the handshake on another
+    // thread and give up on it if the parent never finishes it.
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        let _ = done_tx.send(handshake());
+    });
+    let (config_rx, status_tx) = done_rx
+        .recv_timeout(HANDSHAKE_TIMEOUT)
+        .map_err(|_| anyhow!("Timed out waiting for the parent"))??;
+
+    let config = config_rx
+        .recv()
+        .context("Could not receive configuration")?;
+    let mut osc_out = OscOut::new();
+    osc_out.configure(&config);
+    let metrics = config.metrics_port.and_then(|port| {
+        let metrics = Arc::new(Mutex::new(Metrics::default()));
+        match metrics::serve(port, metrics.clone()) {
+            Ok(()) => Some(metrics),
+            Err(err) => {
+                eprintln!("Could not serve metrics on port {}: {}", port, err);
+                None
+            }
+        }
+    });
+    let (attach_tx, attach_rx) = std::sync::mpsc::channel();
+    let mut sink = AgentSink {
+        osc_out,
+        window: Some((config_rx, status_tx)),
+        linger: config.agent_linger_s.map(|s| Duration::from_secs(s.into())),
+        orphaned: config.orphaned,
+        idle: MappedOutput::idle(&config),
+        detached_since: None,
+        paused: false,
+        attach_tx,
+        attach_rx,
+        metrics,
+        config: config.clone(),
+        osc_in: None,
+        osc_in_error: None,
+        avatar: None,
+    };
+    sink.update_osc_in();
+    joycon::run(config, sink).map_err(|e| anyhow!("{:?}", e))?;
+
+    Ok(())
+}
+
+fn handshake() -> anyhow::Result<Window> {
     let mut address = String::new();
     stdin()
         .read_line(&mut address)
@@ -23,59 +144,371 @@ pub(crate) fn run() -> anyhow::Result<()> {
     let (config_tx, config_rx) =
         ipc::channel::<Configuration>().context("Could not create configuration channel")?;
     let (status_tx, status_rx) =
-        ipc::channel::<Status>().context("Could not create status channel")?;
+        ipc::channel::<Report>().context("Could not create status channel")?;
 
     let sender = IpcSender::connect(address).context("Could not connect to parent")?;
     sender
         .send((config_tx, status_rx))
         .context("Could not send channels")?;
 
-    joycon_main(config_rx, status_tx).map_err(|e| anyhow!("{:?}", e))?;
+    Ok((config_rx, status_tx))
+}
+
+/// Sends readings over OSC and exchanges configuration and status with the
+/// parent process.
+struct AgentSink {
+    osc_out: OscOut,
+    /// The channels to the window, while there is one.
+    window: Option<Window>,
+    /// How long to keep going after the window is gone, waiting for another.
+    linger: Option<Duration>,
+    orphaned: OrphanPolicy,
+    idle: MappedOutput,
+    detached_since: Option<std::time::Instant>,
+    /// Readings are dropped until a window attaches.
+    paused: bool,
+    attach_tx: std::sync::mpsc::Sender<Window>,
+    attach_rx: std::sync::mpsc::Receiver<Window>,
+    metrics: Option<Arc<Mutex<Metrics>>>,
+    /// The configuration from the window, before any avatar profile.
+    config: Configuration,
+    /// Listens for avatar changes while the settings depend on the avatar,
+    /// on the port it was bound to.
+    osc_in: Option<(u16, OscIn)>,
+    /// Why `osc_in` couldn't be bound, as last reported to the window.
+    osc_in_error: Option<String>,
+    /// The avatar last put on, once VRChat has said.
+    avatar: Option<String>,
+}
+
+impl AgentSink {
+    /// Picks up a new window, or gives up on one ever coming.
+    fn check_window(&mut self) {
+        let Some(since) = self.detached_since else {
+            return;
+        };
+        if let Ok(window) = self.attach_rx.try_recv() {
+            eprintln!("Window attached");
+            self.window = Some(window);
+            self.detached_since = None;
+            self.paused = false;
+            if self.osc_in_error.is_some() {
+                self.send_osc_in_error();
+            }
+        } else if self.linger.is_none_or(|linger| since.elapsed() >= linger) {
+            eprintln!("No window attached, exiting");
+            self.osc_out.send(&self.idle, std::time::Instant::now());
+            std::process::exit(0);
+        }
+    }
+
+    /// Forgets the window which went away and starts waiting for another.
+    fn detach(&mut self) {
+        self.window = None;
+        self.detached_since = Some(std::time::Instant::now());
+        if self.linger.is_none() {
+            return;
+        }
+        eprintln!("Window gone, waiting for another to attach");
+        if self.orphaned == OrphanPolicy::Pause {
+            self.osc_out.send(&self.idle, std::time::Instant::now());
+            self.paused = true;
+        }
+        let attach_tx = self.attach_tx.clone();
+        thread::spawn(move || {
+            if let Err(err) = listen(attach_tx) {
+                eprintln!("Could not wait for a window: {:#}", err);
+            }
+        });
+    }
+
+    /// Starts or stops listening for avatar changes, as the configuration
+    /// asks for.
+    fn update_osc_in(&mut self) {
+        let port = self.config.osc_in_port;
+        if !depends_on_avatar(&self.config) {
+            self.osc_in = None;
+            self.report_osc_in(None);
+            return;
+        }
+        if matches!(&self.osc_in, Some((bound, _)) if *bound == port) {
+            return;
+        }
+        // Lets go of the old port first, in case it is the same one.
+        self.osc_in = None;
+        match OscIn::bind(port) {
+            Ok(osc_in) => {
+                self.osc_in = Some((port, osc_in));
+                self.report_osc_in(None);
+            }
+            Err(err) => {
+                eprintln!(
+                    "Could not listen for avatar changes on port {}: {}",
+                    port, err
+                );
+                self.report_osc_in(Some(err.to_string()));
+            }
+        }
+    }
+
+    /// Tells the window about `error` if it is news.
+    fn report_osc_in(&mut self, error: Option<String>) {
+        if self.osc_in_error == error {
+            return;
+        }
+        self.osc_in_error = error;
+        self.send_osc_in_error();
+    }
+
+    fn send_osc_in_error(&mut self) {
+        if let Some((_, status_tx)) = &self.window {
+            if status_tx
+                .send(Report::OscIn(self.osc_in_error.clone()))
+                .is_err()
+            {
+                self.detach();
+            }
+        }
+    }
+
+    /// The next configuration from the window, if it sent one.
+    fn receive_config(&mut self) -> Option<Configuration> {
+        let (config_rx, _) = self.window.as_ref()?;
+        match config_rx.try_recv() {
+            Ok(config) => Some(config),
+            Err(TryRecvError::IpcError(IpcError::Disconnected)) => {
+                self.detach();
+                None
+            }
+            Err(_) => None,
+        }
+    }
+}
 
+/// Whether `config` has settings for particular avatars or an address with
+/// the avatar id in it.
+fn depends_on_avatar(config: &Configuration) -> bool {
+    !config.avatar_profiles.is_empty() || config.osc_address.contains(AVATAR_PLACEHOLDER)
+}
+
+/// Lets one window attach, advertising the server in [`address_path`].
+fn listen(attach_tx: std::sync::mpsc::Sender<Window>) -> anyhow::Result<()> {
+    let (server, name) =
+        ipc::IpcOneShotServer::<Attach>::new().context("Could not create attach server")?;
+    let path = address_path().context("Could not find the configuration directory")?;
+    fs::write(&path, name).context("Could not write the attach address")?;
+    let (_, (config_rx, status_tx, exit_rx)) =
+        server.accept().context("Could not accept window")?;
+    let _ = fs::remove_file(&path);
+    // Waits on its own thread, so that even an agent stuck reading the
+    // controller exits. The channel only closes when the window goes away.
+    thread::spawn(move || {
+        if exit_rx.recv().is_ok() {
+            eprintln!("Asked to exit by the window");
+            std::process::exit(1);
+        }
+    });
+    let _ = attach_tx.send((config_rx, status_tx));
     Ok(())
 }
 
-pub(crate) fn spawn() -> (mpsc::Sender<Configuration>, watch::Receiver<Status>) {
+impl Sink for AgentSink {
+    fn output(&mut self, output: MappedOutput) {
+        if self.paused {
+            return;
+        }
+        self.osc_out.send(&output, std::time::Instant::now());
+        if let Some(metrics) = &self.metrics {
+            metrics.lock().unwrap().osc = self.osc_out.stats();
+        }
+    }
+
+    fn flush(&mut self, now: std::time::Instant) {
+        if !self.paused {
+            self.osc_out.flush(now);
+        }
+    }
+
+    fn status(&mut self, status: Status) {
+        self.check_window();
+        self.osc_out.send_status(&status);
+        if let Some(metrics) = &self.metrics {
+            let mut metrics = metrics.lock().unwrap();
+            metrics.update(&status);
+            metrics.osc = self.osc_out.stats();
+        }
+        if let Some((_, status_tx)) = &self.window {
+            if status_tx.send(Report::Status(status)).is_err() {
+                self.detach();
+            }
+        }
+    }
+
+    fn poll_config(&mut self) -> Option<Configuration> {
+        self.check_window();
+        let received = self.receive_config();
+        let avatar = self
+            .osc_in
+            .as_mut()
+            .and_then(|(_, osc_in)| osc_in.avatar_change());
+        if received.is_none() && avatar.is_none() {
+            return None;
+        }
+        if let Some(config) = received {
+            self.config = config;
+            self.update_osc_in();
+        }
+        if let Some(avatar) = avatar {
+            if self.config.avatar_profiles.contains_key(&avatar) {
+                eprintln!("Switching to the profile for avatar {}", avatar);
+            }
+            self.avatar = Some(avatar);
+        }
+        let config = self.config.for_avatar(self.avatar.as_deref());
+        self.osc_out.set_avatar(self.avatar.as_deref());
+        self.osc_out.configure(&config);
+        self.linger = config.agent_linger_s.map(|s| Duration::from_secs(s.into()));
+        self.orphaned = config.orphaned;
+        self.idle = MappedOutput::idle(&config);
+        Some(config)
+    }
+}
+
+/// Overrides the executable started as the agent.
+const AGENT_PATH_VAR: &str = "OSC_RINGCON_AGENT";
+/// Overrides the arguments passed to the agent, separated by whitespace.
+const AGENT_ARGS_VAR: &str = "OSC_RINGCON_AGENT_ARGS";
+
+/// Works out how to start the agent. By default this executable is started
+/// again with the `agent` argument.
+fn agent_command() -> anyhow::Result<(PathBuf, Vec<OsString>)> {
+    let path = match std::env::var_os(AGENT_PATH_VAR) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::current_exe().context("Could not find the current executable")?,
+    };
+    if !path.is_file() {
+        return Err(anyhow!(
+            "Agent executable {} does not exist",
+            path.display()
+        ));
+    }
+
+    let args = match std::env::var(AGENT_ARGS_VAR) {
+        Ok(args) => args.split_whitespace().map(OsString::from).collect(),
+        Err(std::env::VarError::NotPresent) => vec![OsString::from("agent")],
+        Err(err) => return Err(err).context(AGENT_ARGS_VAR),
+    };
+
+    Ok((path, args))
+}
+
+/// Starts the agent and keeps it running. With `status_json`, every status is
+/// also written to stdout as a line of JSON, and the agent's own output is
+/// discarded so that it doesn't get mixed in.
+///
+/// Besides the status, the agent reports why it couldn't listen on
+/// `osc_in_port`, if it couldn't.
+pub(crate) fn spawn(
+    status_json: bool,
+) -> (
+    mpsc::Sender<Configuration>,
+    watch::Receiver<Status>,
+    watch::Receiver<Option<String>>,
+) {
     let (config_sink, mut config_rx) = mpsc::channel(4);
-    let (mut status_tx, status_receiver) = watch::channel(Status::NotConnected);
+    let (status_tx, status_receiver) = watch::channel(Status::NotConnected);
+    let (osc_in_tx, osc_in_receiver) = watch::channel(None);
+    let mut reports = Reports {
+        status_tx,
+        osc_in_tx,
+        status_json,
+    };
 
     tokio::task::spawn(async move {
-        let mut last_config = None;
+        let (path, args) = match agent_command() {
+            Ok(command) => command,
+            Err(err) => {
+                eprintln!("Could not start agent: {:#}", err);
+                return;
+            }
+        };
+
+        // Whether the agent may outlive this process depends on the settings.
+        let Some(config) = config_rx.next().await else {
+            return;
+        };
+        let mut last_config = Some(config);
+        let mut attached = match attach() {
+            Ok(attached) => attached,
+            Err(err) => {
+                eprintln!("Could not attach to running agent {:?}", err);
+                None
+            }
+        };
         loop {
+            if let Some((mut config_tx, mut status_rx, exit_tx)) = attached.take() {
+                eprintln!("attached to running agent");
+                let result = manage(
+                    &mut last_config,
+                    &mut config_rx,
+                    &mut config_tx,
+                    &mut status_rx,
+                    &mut reports,
+                    Agent::Attached(exit_tx),
+                )
+                .await;
+                match result {
+                    Ok(()) => break,
+                    Err(err) => eprintln!("Agent died {:?}", err),
+                }
+                continue;
+            }
+
             eprintln!("spawning agent");
-            let (server, client) = ipc_channel::ipc::IpcOneShotServer::<(
-                ipc::IpcSender<Configuration>,
-                ipc::IpcReceiver<Status>,
-            )>::new()
-            .unwrap();
-            let mut child = tokio::process::Command::new(std::env::current_exe().unwrap())
-                .arg("agent")
+            let (server, client) = ipc::IpcOneShotServer::<Handshake>::new().unwrap();
+            let mut child = match tokio::process::Command::new(&path)
+                .args(&args)
                 .stdin(Stdio::piped())
-                .stdout(Stdio::inherit())
+                .stdout(if status_json {
+                    Stdio::null()
+                } else {
+                    Stdio::inherit()
+                })
                 .stderr(Stdio::inherit())
                 .spawn()
-                .unwrap();
-            child
-                .stdin
-                .take()
-                .unwrap()
-                .write_all(client.as_bytes())
-                .await
-                .unwrap();
-            let (mut config_tx, mut status_rx) = tokio::task::spawn_blocking(|| {
-                let (_, (config_tx, status_rx)) = server.accept().unwrap();
-                (config_tx, status_rx.to_stream())
-            })
-            .await
-            .unwrap();
+            {
+                Ok(child) => child,
+                Err(err) => {
+                    eprintln!("Could not start agent {}: {}", path.display(), err);
+                    return;
+                }
+            };
+            let lingers = last_config
+                .as_ref()
+                .is_some_and(|config| config.agent_linger_s.is_some());
+            if !lingers {
+                if let Some(handle) = child.raw_handle() {
+                    if let Err(err) = kill_on_exit(handle) {
+                        eprintln!("Could not tie the agent to this process {:?}", err);
+                    }
+                }
+            }
+            let (mut config_tx, mut status_rx) = match connect(&mut child, server, client).await {
+                Ok(channels) => channels,
+                Err(err) => {
+                    eprintln!("Agent handshake failed {:?}", err);
+                    let _ = child.kill().await;
+                    continue;
+                }
+            };
 
             match manage(
                 &mut last_config,
                 &mut config_rx,
                 &mut config_tx,
                 &mut status_rx,
-                &mut status_tx,
-                child,
+                &mut reports,
+                Agent::Child(child),
             )
             .await
             {
@@ -85,20 +518,101 @@ pub(crate) fn spawn() -> (mpsc::Sender<Configuration>, watch::Receiver<Status>)
         }
     });
 
-    (config_sink, status_receiver)
+    (config_sink, status_receiver, osc_in_receiver)
+}
+
+/// A job which kills the processes in it once this process exits, however
+/// it exits.
+static JOB: OnceLock<Option<isize>> = OnceLock::new();
+
+/// Makes sure the agent doesn't outlive this process, even if it is killed.
+fn kill_on_exit(process: RawHandle) -> anyhow::Result<()> {
+    let job = JOB.get_or_init(|| unsafe {
+        let job = CreateJobObjectW(std::ptr::null(), PCWSTR(std::ptr::null())).ok()?;
+        let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+        limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &limits as *const _ as *const _,
+            std::mem::size_of_val(&limits) as u32,
+        )
+        .as_bool()
+        .then_some(job.0)
+    });
+    let job = job.context("Could not create a job object")?;
+    unsafe { AssignProcessToJobObject(HANDLE(job), HANDLE(process as isize)) }
+        .ok()
+        .context("Could not assign the agent to the job object")
+}
+
+/// Takes over an agent left running by a previous window, if one is waiting.
+fn attach() -> anyhow::Result<Option<Attached>> {
+    let Some(path) = address_path() else {
+        return Ok(None);
+    };
+    let Ok(name) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    // Only one window can attach, and a leftover address is stale.
+    let _ = fs::remove_file(&path);
+    let Ok(sender) = IpcSender::<Attach>::connect(name) else {
+        return Ok(None);
+    };
+
+    let (config_tx, config_rx) =
+        ipc::channel::<Configuration>().context("Could not create configuration channel")?;
+    let (status_tx, status_rx) =
+        ipc::channel::<Report>().context("Could not create status channel")?;
+    let (exit_tx, exit_rx) = ipc::channel::<()>().context("Could not create exit channel")?;
+    sender
+        .send((config_rx, status_tx, exit_rx))
+        .context("Could not send channels")?;
+    Ok(Some((config_tx, status_rx.to_stream(), exit_tx)))
+}
+
+async fn connect(
+    child: &mut tokio::process::Child,
+    server: ipc::IpcOneShotServer<Handshake>,
+    client: String,
+) -> anyhow::Result<(IpcSender<Configuration>, IpcStream<Report>)> {
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(client.as_bytes())
+        .await
+        .context("Could not send IPC address")?;
+
+    // accept can't be cancelled, so an agent which never connects leaves this
+    // blocking thread behind.
+    let accept = tokio::task::spawn_blocking(move || server.accept());
+    let (_, (config_tx, status_rx)) = tokio::time::timeout(HANDSHAKE_TIMEOUT, accept)
+        .await
+        .context("Timed out waiting for channels")?
+        .context("Accepting channels panicked")?
+        .context("Could not receive channels")?;
+    Ok((config_tx, status_rx.to_stream()))
 }
 
 async fn manage(
     last_config: &mut Option<Configuration>,
     config_rx: &mut mpsc::Receiver<Configuration>,
     config_tx: &mut IpcSender<Configuration>,
-    status_rx: &mut IpcStream<Status>,
-    status_tx: &mut watch::Sender<Status>,
-    mut child: tokio::process::Child,
+    status_rx: &mut IpcStream<Report>,
+    reports: &mut Reports,
+    mut agent: Agent,
 ) -> anyhow::Result<()> {
     if let Some(last_config) = last_config.clone() {
         config_tx.send(last_config)?;
     }
+    // The agent reports it again if it still can't listen.
+    reports.osc_in(None);
+
+    // The agent only reports regularly once it has found a controller. While
+    // it does, silence means it is stuck, e.g. reading from a half-dead link.
+    let mut expect_activity = false;
+    let mut deadline = Instant::now() + WATCHDOG_TIMEOUT;
 
     loop {
         tokio::select! {
@@ -111,17 +625,132 @@ async fn manage(
                 *last_config = Some(config.clone());
                 config_tx.send(config).context("Agent send failed")?;
             }
-            (status, _) = status_rx.into_future() => {
-                let status = if let Some(status) = status {
-                    status.context("Agent receive failed")?
-                } else {
-                    return Err(anyhow!("Agent connection closed"));
+            (report, _) = status_rx.into_future() => {
+                let status = match report {
+                    Some(report) => match report.context("Agent receive failed")? {
+                        Report::Status(status) => status,
+                        Report::OscIn(error) => {
+                            reports.osc_in(error);
+                            continue;
+                        }
+                    },
+                    None => return Err(anyhow!("Agent connection closed")),
                 };
-                status_tx.send(status).context("Status forward failed")?;
+                let idle_poll_ms = last_config.as_ref().and_then(|c| c.idle_poll_ms);
+                let timeout = watchdog_timeout(&status, idle_poll_ms);
+                expect_activity = timeout.is_some();
+                deadline = Instant::now() + timeout.unwrap_or(WATCHDOG_TIMEOUT);
+                reports.status(status)?;
             }
-            _ = child.wait() => {
+            _ = wait(&mut agent) => {
                 return Err(anyhow!("Agent terminated"));
             }
+            _ = tokio::time::sleep_until(deadline), if expect_activity => {
+                match &mut agent {
+                    Agent::Child(child) => {
+                        child.kill().await.context("Could not kill unresponsive agent")?;
+                    }
+                    // Stops it before the next one is started. It may have
+                    // exited already, which is just as good.
+                    Agent::Attached(exit_tx) => {
+                        let _ = exit_tx.send(());
+                    }
+                }
+                // The agent can't reset the avatar anymore, so do it here.
+                if let Some(config) = last_config {
+                    send_idle(config);
+                }
+                reports.status(Status::Disconnected)?;
+                return Err(anyhow!("Agent stopped responding"));
+            }
         };
     }
 }
+
+/// How long the agent may stay silent after reporting `status` before it
+/// counts as stuck, or `None` when it only reports once something changes.
+fn watchdog_timeout(status: &Status, idle_poll_ms: Option<u32>) -> Option<Duration> {
+    match status {
+        // Each setup step gives up within `STEP_TIME_LIMIT` and reports the
+        // next step or the failure.
+        Status::Initializing(_) => Some(WATCHDOG_TIMEOUT),
+        Status::NoRingCon | Status::Active(..) => {
+            Some(ACTIVE_WATCHDOG_TIMEOUT + Duration::from_millis(idle_poll_ms.unwrap_or(0).into()))
+        }
+        _ => None,
+    }
+}
+
+/// Waits for the agent to exit, or forever for an agent which isn't a child.
+async fn wait(agent: &mut Agent) {
+    match agent {
+        Agent::Child(child) => {
+            let _ = child.wait().await;
+        }
+        Agent::Attached(_) => futures::future::pending().await,
+    }
+}
+
+/// Passes on what the agent reports to the rest of the window.
+struct Reports {
+    status_tx: watch::Sender<Status>,
+    osc_in_tx: watch::Sender<Option<String>>,
+    status_json: bool,
+}
+
+impl Reports {
+    fn status(&mut self, status: Status) -> anyhow::Result<()> {
+        if self.status_json {
+            println!("{}", serde_json::to_string(&status)?);
+        }
+        self.status_tx.send(status).context("Status forward failed")
+    }
+
+    fn osc_in(&mut self, error: Option<String>) {
+        // Nothing may be reading this, as with the service.
+        let _ = self.osc_in_tx.send(error);
+    }
+}
+
+/// Resets the avatar, for when the agent can't.
+pub(crate) fn send_idle(config: &Configuration) {
+    let mut osc_out = OscOut::new();
+    osc_out.configure(config);
+    osc_out.send(&MappedOutput::idle(config), std::time::Instant::now());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use osc_ringcon::{
+        joycon::STEP_TIME_LIMIT,
+        messages::{InitializationStep, Status},
+    };
+
+    use super::{watchdog_timeout, ACTIVE_WATCHDOG_TIMEOUT};
+
+    #[test]
+    fn setup_steps_report_before_the_watchdog() {
+        // Waiting for the Ring-Con in Step4 included, so the agent isn't
+        // killed while it waits.
+        for step in InitializationStep::ALL {
+            let timeout = watchdog_timeout(&Status::Initializing(step), None);
+            assert!(
+                timeout.is_some_and(|timeout| STEP_TIME_LIMIT < timeout),
+                "{:?}",
+                step
+            );
+        }
+    }
+
+    #[test]
+    fn watchdog_only_runs_while_reporting() {
+        assert_eq!(
+            watchdog_timeout(&Status::NoRingCon, Some(250)),
+            Some(ACTIVE_WATCHDOG_TIMEOUT + Duration::from_millis(250))
+        );
+        assert_eq!(watchdog_timeout(&Status::NotConnected, None), None);
+        assert_eq!(watchdog_timeout(&Status::Disconnected, None), None);
+    }
+}
@@ -0,0 +1,14 @@
+use iced::canvas::Program;
+use iced::{Canvas, Element, Length};
+
+/// Wraps a canvas program in an element of the given size.
+///
+/// iced only turns a canvas into an element when its messages are
+/// `'static`, which every view here satisfies.
+pub(crate) fn element<'a, Message: 'static>(
+    program: impl Program<Message> + 'a,
+    width: Length,
+    height: Length,
+) -> Element<'a, Message> {
+    Canvas::new(program).width(width).height(height).into()
+}
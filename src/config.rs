@@ -0,0 +1,221 @@
+use std::{
+    ffi::OsString,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use osc_ringcon::messages::{Configuration, CONFIG_VERSION};
+
+fn path() -> Option<PathBuf> {
+    let mut path = PathBuf::from(std::env::var_os("APPDATA")?);
+    path.push("osc-ringcon");
+    path.push("config.toml");
+    Some(path)
+}
+
+/// Whether settings have been saved before, even if they can't be read.
+pub(crate) fn exists() -> bool {
+    path().is_some_and(|path| path.exists() || with_suffix(&path, ".bak").exists())
+}
+
+/// Reads the saved configuration, if there is a usable one.
+pub(crate) fn load() -> Option<Configuration> {
+    load_from(&path()?)
+}
+
+/// Reads a configuration from somewhere other than the usual place. If it is
+/// missing or invalid, the copy kept by the last save is used instead.
+pub(crate) fn load_from(path: &Path) -> Option<Configuration> {
+    parse(path).or_else(|| {
+        let backup = with_suffix(path, ".bak");
+        let config = parse(&backup)?;
+        eprintln!("Using the backup configuration {}", backup.display());
+        Some(config)
+    })
+}
+
+fn parse(path: &Path) -> Option<Configuration> {
+    let source = fs::read_to_string(path).ok()?;
+    let parsed = toml::from_str(&source).and_then(|value| migrate(value).try_into());
+    match parsed {
+        Ok(config) => Some(config),
+        Err(err) => {
+            eprintln!("Ignoring invalid configuration {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// `path` with `suffix` added to the file name, for files kept next to it.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Upgrades settings written by an older version of the program to
+/// [`CONFIG_VERSION`], one version at a time.
+fn migrate(mut value: toml::Value) -> toml::Value {
+    let table = match value.as_table_mut() {
+        Some(table) => table,
+        None => return value,
+    };
+    let version = match table.get("version") {
+        Some(version) => version.as_integer().unwrap_or(i64::MAX),
+        None => 0,
+    };
+    if version > CONFIG_VERSION.into() {
+        eprintln!(
+            "Configuration is from a newer version ({}), settings this version doesn't know will be lost when it is saved",
+            version
+        );
+        return value;
+    }
+
+    if version < 1 {
+        eprintln!("Upgrading configuration from version {} to 1", version);
+        // Turning `idle_poll_ms` off leaves it out of the file, but before
+        // version 1 it was only missing from files older than the setting.
+        if let Some(ms) = Configuration::default().idle_poll_ms {
+            table
+                .entry("idle_poll_ms")
+                .or_insert_with(|| i64::from(ms).into());
+        }
+    }
+
+    table.insert("version".to_string(), CONFIG_VERSION.into());
+    value
+}
+
+pub(crate) fn save(config: &Configuration) -> anyhow::Result<()> {
+    let path = path().context("Could not find the configuration directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Could not create the configuration directory")?;
+    }
+    save_to(&path, config)
+}
+
+/// Replaces the configuration at `path` without ever leaving a partly written
+/// file there, keeping the last good one as a backup.
+fn save_to(path: &Path, config: &Configuration) -> anyhow::Result<()> {
+    // Go through Value so that tables are written after plain values.
+    let mut value = toml::Value::try_from(config)?;
+    // Settings loaded from a newer file are now in this version's format.
+    value["version"] = CONFIG_VERSION.into();
+    let source = toml::to_string(&value)?;
+
+    let temp = with_suffix(path, ".tmp");
+    let mut file = fs::File::create(&temp).context("Could not write the configuration")?;
+    file.write_all(source.as_bytes())
+        .and_then(|()| file.sync_all())
+        .context("Could not write the configuration")?;
+    drop(file);
+
+    // Only a file which loads is worth going back to.
+    if parse(path).is_some() {
+        fs::copy(path, with_suffix(path, ".bak")).context("Could not back up the configuration")?;
+    }
+    fs::rename(&temp, path).context("Could not replace the configuration")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_ringcon::messages::{Configuration, CONFIG_VERSION};
+
+    use super::{load_from, migrate, save_to, with_suffix};
+
+    #[test]
+    fn unversioned_file_is_upgraded() {
+        let source = r#"
+            udp_address = "127.0.0.1:9000"
+            osc_address = "/avatar/parameters/ringcon_flex"
+            in_center = 15
+            out_idle = 0.0
+
+            [in_range]
+            start = 7
+            end = 24
+
+            [out_range]
+            start = 0.5
+            end = 1.0
+        "#;
+        let config: Configuration = migrate(toml::from_str(source).unwrap()).try_into().unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.in_range, 7..=24);
+        assert_eq!(config.reinit_attempts, 3);
+    }
+
+    #[test]
+    fn unversioned_file_gets_defaults() {
+        // All that the first version saved, set as they are by default.
+        let source = r#"
+            udp_address = "127.0.0.1:9000"
+            osc_address = "/avatar/parameters/ringcon_flex"
+            in_center = 15
+            out_idle = 0.0
+
+            [in_range]
+            start = 7
+            end = 24
+
+            [out_range]
+            start = 0.5
+            end = 1.0
+        "#;
+        let config: Configuration = migrate(toml::from_str(source).unwrap()).try_into().unwrap();
+        assert_eq!(
+            toml::Value::try_from(config).unwrap(),
+            toml::Value::try_from(Configuration::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn turned_off_setting_stays_off() {
+        let value = toml::Value::try_from(Configuration {
+            idle_poll_ms: None,
+            ..Configuration::default()
+        })
+        .unwrap();
+        let config: Configuration = migrate(value).try_into().unwrap();
+        assert_eq!(config.idle_poll_ms, None);
+    }
+
+    #[test]
+    fn newer_file_is_left_alone() {
+        let value: toml::Value = toml::from_str("version = 1000").unwrap();
+        assert_eq!(migrate(value.clone()), value);
+    }
+
+    #[test]
+    fn partial_write_falls_back_to_backup() {
+        let dir = std::env::temp_dir().join(format!("osc-ringcon-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        let first = Configuration {
+            in_center: 12,
+            ..Configuration::default()
+        };
+        save_to(&path, &first).unwrap();
+        let second = Configuration {
+            in_center: 18,
+            ..Configuration::default()
+        };
+        save_to(&path, &second).unwrap();
+        assert!(!with_suffix(&path, ".tmp").exists());
+        assert_eq!(load_from(&path).unwrap().in_center, 18);
+
+        // Cut the file off as a crash in the middle of writing it would.
+        let source = std::fs::read_to_string(&path).unwrap();
+        let cut = source.find("/avatar").unwrap() + 3;
+        std::fs::write(&path, &source[..cut]).unwrap();
+        assert_eq!(load_from(&path).unwrap().in_center, 12);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+use iced::canvas::event::{self, Event};
+use iced::canvas::{Cursor, Frame, Geometry, Path, Program, Stroke};
+use iced::mouse;
+use iced::{Color, Element, Length, Point, Rectangle, Size};
+
+use crate::canvas;
+
+/// How close to a point, in pixels, a press has to be to grab it.
+const GRAB_RADIUS: f32 = 8.0;
+const POINT_RADIUS: f32 = 4.0;
+
+/// Changes made by dragging on the curve editor.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum CurveEdit {
+    /// An existing point was grabbed.
+    Grab(usize),
+    /// A new point was placed and grabbed.
+    Add((f32, f32)),
+    /// The grabbed point was dragged here.
+    Move((f32, f32)),
+    /// The point was removed with a right click.
+    Remove(usize),
+    /// The grabbed point was let go, so the curve can be applied.
+    Release,
+}
+
+/// Draws `points` in a square going from 0 to 1 on both axes. Left click
+/// adds or drags a point and right click removes one.
+pub(crate) fn view<'a, Message: 'static>(
+    points: &'a [(f32, f32)],
+    grabbed: Option<usize>,
+    color: Color,
+    on_edit: fn(CurveEdit) -> Message,
+) -> Element<'a, Message> {
+    canvas::element(
+        CurveEditor {
+            points,
+            grabbed,
+            color,
+            on_edit,
+        },
+        Length::Units(120),
+        Length::Units(120),
+    )
+}
+
+struct CurveEditor<'a, Message> {
+    points: &'a [(f32, f32)],
+    grabbed: Option<usize>,
+    color: Color,
+    on_edit: fn(CurveEdit) -> Message,
+}
+
+impl<Message> CurveEditor<'_, Message> {
+    fn to_screen(size: Size, (x, y): (f32, f32)) -> Point {
+        Point::new(x * size.width, (1.0 - y) * size.height)
+    }
+
+    fn from_screen(size: Size, point: Point) -> (f32, f32) {
+        (
+            (point.x / size.width).clamp(0.0, 1.0),
+            (1.0 - point.y / size.height).clamp(0.0, 1.0),
+        )
+    }
+
+    /// The point under `position`, if any.
+    fn point_at(&self, size: Size, position: Point) -> Option<usize> {
+        self.points
+            .iter()
+            .position(|&point| Self::to_screen(size, point).distance(position) <= GRAB_RADIUS)
+    }
+}
+
+impl<Message> Program<Message> for CurveEditor<'_, Message> {
+    fn update(
+        &mut self,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let size = bounds.size();
+        let edit = match event {
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if self.grabbed.is_some() =>
+            {
+                Some(CurveEdit::Release)
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if self.grabbed.is_some() => cursor
+                .position_in(&bounds)
+                .map(|position| CurveEdit::Move(Self::from_screen(size, position))),
+            Event::Mouse(mouse::Event::ButtonPressed(button)) => cursor
+                .position_in(&bounds)
+                .and_then(|position| match (button, self.point_at(size, position)) {
+                    (mouse::Button::Left, Some(index)) => Some(CurveEdit::Grab(index)),
+                    (mouse::Button::Left, None) => {
+                        Some(CurveEdit::Add(Self::from_screen(size, position)))
+                    }
+                    (mouse::Button::Right, Some(index)) => Some(CurveEdit::Remove(index)),
+                    _ => None,
+                }),
+            _ => None,
+        };
+        match edit {
+            Some(edit) => (event::Status::Captured, Some((self.on_edit)(edit))),
+            None => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let size = frame.size();
+        let faint = Color::from_rgb(0.6, 0.6, 0.6);
+
+        frame.stroke(
+            &Path::rectangle(Point::ORIGIN, size),
+            Stroke::default().with_color(faint),
+        );
+        // Where the output would be without a curve.
+        frame.stroke(
+            &Path::line(
+                Self::to_screen(size, (0.0, 0.0)),
+                Self::to_screen(size, (1.0, 1.0)),
+            ),
+            Stroke::default().with_color(faint),
+        );
+
+        let mut sorted = self.points.to_vec();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if let (Some(&(_, first)), Some(&(_, last))) = (sorted.first(), sorted.last()) {
+            // Flat past the ends, as the mapping treats them.
+            sorted.insert(0, (0.0, first));
+            sorted.push((1.0, last));
+            let path = Path::new(|builder| {
+                builder.move_to(Self::to_screen(size, sorted[0]));
+                for &point in &sorted[1..] {
+                    builder.line_to(Self::to_screen(size, point));
+                }
+            });
+            frame.stroke(
+                &path,
+                Stroke::default().with_color(self.color).with_width(2.0),
+            );
+        }
+
+        for (index, &point) in self.points.iter().enumerate() {
+            let radius = if Some(index) == self.grabbed {
+                POINT_RADIUS * 1.5
+            } else {
+                POINT_RADIUS
+            };
+            frame.fill(
+                &Path::circle(Self::to_screen(size, point), radius),
+                self.color,
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,63 @@
+use std::net::{IpAddr, SocketAddr};
+
+use crate::firewall::{self, SendCheck};
+
+/// The port VRChat listens on for OSC unless told otherwise.
+const VRCHAT_DEFAULT_PORT: u16 = 9000;
+
+/// Whether some program is listening on `port` of `ip`, judged by an empty
+/// packet not being answered with "port unreachable".
+///
+/// Binding the port ourselves would tell the same, but would take it from
+/// VRChat if it started at that moment.
+fn is_listening(ip: IpAddr, port: u16) -> bool {
+    matches!(
+        firewall::check_send(SocketAddr::new(ip, port)),
+        SendCheck::Sent { .. }
+    )
+}
+
+/// Looks for the port VRChat is listening on when nothing listens on the
+/// configured one. Only local targets can be checked.
+///
+/// VRChat's OSC folder only describes avatar parameters, so the ports tried
+/// are the configured one and VRChat's default. Each probe waits for a reply,
+/// so this blocks for up to a second.
+pub(crate) fn discover(current: SocketAddr) -> Option<SocketAddr> {
+    if !current.ip().is_loopback() || is_listening(current.ip(), current.port()) {
+        return None;
+    }
+    [VRCHAT_DEFAULT_PORT]
+        .into_iter()
+        .filter(|&port| port != current.port())
+        .find(|&port| is_listening(current.ip(), port))
+        .map(|port| SocketAddr::new(current.ip(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+
+    use super::{discover, is_listening};
+
+    #[test]
+    fn listener_is_detected() {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        assert!(is_listening(Ipv4Addr::LOCALHOST.into(), port));
+        drop(socket);
+        assert!(!is_listening(Ipv4Addr::LOCALHOST.into(), port));
+    }
+
+    #[test]
+    fn listening_target_is_kept() {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+        assert_eq!(discover(socket.local_addr().unwrap()), None);
+    }
+
+    #[test]
+    fn remote_target_is_kept() {
+        let remote = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 168, 0, 2), 9001));
+        assert_eq!(discover(remote), None);
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use osc_ringcon::messages::Status;
+use windows::Win32::Foundation::SYSTEMTIME;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+/// How many state changes are kept.
+const CAPACITY: usize = 20;
+
+/// A local time of day, for showing when something happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ClockTime {
+    pub hour: u16,
+    pub minute: u16,
+    pub second: u16,
+}
+
+impl ClockTime {
+    pub fn now() -> Self {
+        let mut time = SYSTEMTIME::default();
+        unsafe { GetLocalTime(&mut time) };
+        ClockTime {
+            hour: time.wHour,
+            minute: time.wMinute,
+            second: time.wSecond,
+        }
+    }
+}
+
+impl fmt::Display for ClockTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+/// Recent changes of the connection state, newest last.
+#[derive(Debug, Default)]
+pub(crate) struct EventLog {
+    entries: VecDeque<(ClockTime, Status)>,
+}
+
+impl EventLog {
+    /// Records `status` if it is a different state from the last one.
+    /// Readings and setup steps don't count as changes.
+    pub fn push(&mut self, status: &Status, time: ClockTime) {
+        if matches!(self.entries.back(), Some((_, last)) if last.code() == status.code()) {
+            return;
+        }
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((time, status.clone()));
+    }
+
+    /// The changes, newest first.
+    pub fn iter(&self) -> impl Iterator<Item = &(ClockTime, Status)> {
+        self.entries.iter().rev()
+    }
+}
+
+/// The message describing a change to `status`.
+pub(crate) fn label_key(status: &Status) -> &'static str {
+    match status {
+        Status::NotConnected => "log-not-connected",
+        Status::Initializing(_) => "log-initializing",
+        Status::NoRingCon => "log-no-ringcon",
+        Status::Active(_) => "log-active",
+        Status::Disconnected => "log-disconnected",
+        Status::UnsupportedDevice(_) => "log-unsupported-device",
+        Status::MultipleDevices(_) => "log-multiple-devices",
+        Status::HardwareError(_) => "log-hardware-error",
+        Status::UnsupportedFirmware => "log-unsupported-firmware",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_ringcon::messages::{InitializationStep, Status};
+
+    use super::{ClockTime, EventLog, CAPACITY};
+
+    fn time(second: u16) -> ClockTime {
+        ClockTime {
+            hour: 12,
+            minute: 0,
+            second,
+        }
+    }
+
+    #[test]
+    fn only_changes_are_kept() {
+        let mut log = EventLog::default();
+        log.push(&Status::NotConnected, time(0));
+        log.push(
+            &Status::Initializing(InitializationStep::Configuring),
+            time(1),
+        );
+        log.push(&Status::Initializing(InitializationStep::McuState), time(2));
+        log.push(&Status::NoRingCon, time(3));
+        log.push(&Status::NoRingCon, time(4));
+
+        let entries: Vec<_> = log
+            .iter()
+            .map(|(time, status)| (*time, status.code()))
+            .collect();
+        assert_eq!(entries, [(time(3), 2), (time(1), 1), (time(0), 0)]);
+    }
+
+    #[test]
+    fn oldest_are_dropped() {
+        let mut log = EventLog::default();
+        for second in 0..CAPACITY as u16 + 5 {
+            let status = if second % 2 == 0 {
+                Status::NoRingCon
+            } else {
+                Status::Disconnected
+            };
+            log.push(&status, time(second));
+        }
+        assert_eq!(log.iter().count(), CAPACITY);
+        assert_eq!(log.iter().last().unwrap().0, time(5));
+    }
+
+    #[test]
+    fn time_is_padded() {
+        let time = ClockTime {
+            hour: 9,
+            minute: 5,
+            second: 0,
+        };
+        assert_eq!(time.to_string(), "09:05:00");
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+/// How long to wait for the system to report that nothing is listening.
+const REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// What happened when trying to send to the OSC target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SendCheck {
+    /// Sent, and nothing said that it didn't arrive.
+    Sent { local: bool },
+    /// Sent, but the target reported that nothing is listening on the port.
+    NothingListening { local: bool },
+    /// The system refused to send, which is usually a firewall rule.
+    Blocked,
+}
+
+impl SendCheck {
+    /// Fluent message explaining the result.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            SendCheck::Sent { local: true } => "firewall-local-sent",
+            SendCheck::Sent { local: false } => "firewall-remote-sent",
+            SendCheck::NothingListening { local: true } => "firewall-local-nothing-listening",
+            SendCheck::NothingListening { local: false } => "firewall-remote-nothing-listening",
+            SendCheck::Blocked => "firewall-blocked",
+        }
+    }
+}
+
+/// Sends an empty packet to `target` and waits briefly for an error.
+///
+/// A connected UDP socket reports the ICMP "port unreachable" reply as an
+/// error on the next receive, so that is the only sign of nothing listening.
+pub(crate) fn check_send(target: SocketAddr) -> SendCheck {
+    let local = target.ip().is_loopback();
+    match try_send(target) {
+        Ok(()) => SendCheck::Sent { local },
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            SendCheck::NothingListening { local }
+        }
+        Err(err) => {
+            eprintln!("Could not send to {}: {}", target, err);
+            SendCheck::Blocked
+        }
+    }
+}
+
+fn try_send(target: SocketAddr) -> io::Result<()> {
+    // Binding to loopback doesn't make Windows Firewall ask anything.
+    let bind: SocketAddr = match (target.ip().is_loopback(), target) {
+        (true, SocketAddr::V4(_)) => (Ipv4Addr::LOCALHOST, 0).into(),
+        (true, SocketAddr::V6(_)) => (Ipv6Addr::LOCALHOST, 0).into(),
+        (false, SocketAddr::V4(_)) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        (false, SocketAddr::V6(_)) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.connect(target)?;
+    socket.send(&[])?;
+    socket.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    match socket.recv(&mut [0; 1]) {
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            Ok(())
+        }
+        Err(err) => Err(err),
+        Ok(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+
+    use super::{check_send, SendCheck};
+
+    #[test]
+    fn listening_target() {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let result = check_send(socket.local_addr().unwrap());
+        assert_eq!(result, SendCheck::Sent { local: true });
+    }
+
+    #[test]
+    fn closed_port() {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let target = socket.local_addr().unwrap();
+        drop(socket);
+        let result = check_send(target);
+        assert_eq!(result, SendCheck::NothingListening { local: true });
+    }
+}
@@ -0,0 +1,84 @@
+use std::ops::RangeInclusive;
+
+use iced::canvas::{Cursor, Frame, Geometry, Path, Program, Stroke};
+use iced::{Color, Element, Length, Point, Rectangle, Size};
+
+use crate::{canvas, style::Tone};
+
+/// A bar showing the flex against the input range, with a tick at the
+/// center, so that calibrating is a matter of keeping the bar in the band.
+pub(crate) fn view<'a, Message: 'static>(
+    span: RangeInclusive<u8>,
+    in_range: RangeInclusive<u8>,
+    center: u8,
+    flex: u8,
+    color: Color,
+) -> Element<'a, Message> {
+    canvas::element(
+        Gauge {
+            span,
+            in_range,
+            center,
+            flex,
+            color,
+        },
+        Length::Fill,
+        Length::Units(20),
+    )
+}
+
+struct Gauge {
+    /// Readings at the ends of this range are drawn at the left and right.
+    span: RangeInclusive<u8>,
+    in_range: RangeInclusive<u8>,
+    center: u8,
+    flex: u8,
+    color: Color,
+}
+
+/// Where `flex` is drawn, from 0 at the start of `span` to 1 at its end.
+fn position(flex: u8, span: &RangeInclusive<u8>) -> f32 {
+    let width = f32::from(span.end().saturating_sub(*span.start())).max(1.0);
+    ((f32::from(flex) - f32::from(*span.start())) / width).clamp(0.0, 1.0)
+}
+
+impl<Message> Program<Message> for Gauge {
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let (width, height) = (frame.width(), frame.height());
+        let x = |flex| width * position(flex, &self.span);
+
+        frame.fill_rectangle(Point::ORIGIN, frame.size(), Color::from_rgb(0.6, 0.6, 0.6));
+        let (start, end) = (x(*self.in_range.start()), x(*self.in_range.end()));
+        frame.fill_rectangle(
+            Point::new(start, 0.0),
+            Size::new(end - start, height),
+            Color {
+                a: 0.5,
+                ..Tone::Good.color()
+            },
+        );
+        frame.fill_rectangle(Point::ORIGIN, Size::new(x(self.flex), height), self.color);
+
+        let center = x(self.center);
+        frame.stroke(
+            &Path::line(Point::new(center, 0.0), Point::new(center, height)),
+            Stroke::default().with_color(Color::BLACK).with_width(2.0),
+        );
+
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::position;
+
+    #[test]
+    fn positions_are_clamped_to_span() {
+        assert_eq!(position(7, &(7..=17)), 0.0);
+        assert_eq!(position(12, &(7..=17)), 0.5);
+        assert_eq!(position(30, &(7..=17)), 1.0);
+        assert_eq!(position(15, &(15..=15)), 0.0);
+    }
+}
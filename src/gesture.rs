@@ -0,0 +1,210 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::messages::{ClenchSettings, Configuration};
+
+/// Recognizes a quick squeeze of the Ring-Con as a one-off event, and turns
+/// it into a pulse which lasts long enough for VRChat to see it.
+pub struct ClenchDetector {
+    settings: ClenchSettings,
+    /// Readings within the gesture window, oldest first.
+    recent: VecDeque<(u8, Instant)>,
+    pulse_until: Option<Instant>,
+    cooldown_until: Option<Instant>,
+}
+
+impl ClenchDetector {
+    pub fn new(settings: ClenchSettings) -> Self {
+        Self {
+            settings,
+            recent: VecDeque::new(),
+            pulse_until: None,
+            cooldown_until: None,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: ClenchSettings) {
+        self.settings = settings;
+    }
+
+    /// Records a reading and returns whether the pulse is on.
+    pub fn update(&mut self, flex: u8, now: Instant) -> bool {
+        if flex == 0 {
+            self.recent.clear();
+        } else {
+            let window = Duration::from_millis(self.settings.window_ms.into());
+            while let Some(&(_, time)) = self.recent.front() {
+                if now.duration_since(time) <= window {
+                    break;
+                }
+                self.recent.pop_front();
+            }
+            self.recent.push_back((flex, now));
+
+            // Squeezing raises the reading.
+            let lowest = self.recent.iter().map(|&(flex, _)| flex).min().unwrap();
+            let cooled_down = self.cooldown_until.is_none_or(|until| now >= until);
+            if flex - lowest >= self.settings.min_delta && cooled_down {
+                let pulse = Duration::from_millis(self.settings.pulse_ms.into());
+                let cooldown = Duration::from_millis(self.settings.cooldown_ms.into());
+                self.pulse_until = Some(now + pulse);
+                self.cooldown_until = Some(now + cooldown);
+                // The same squeeze shouldn't count again once the cooldown is over.
+                self.recent.clear();
+                self.recent.push_back((flex, now));
+            }
+        }
+
+        self.pulse_until.is_some_and(|until| now < until)
+    }
+}
+
+/// Latches on once the Ring-Con has been held squeezed for a while, and off
+/// once it is let go. Letting go takes a lower reading than squeezing, so
+/// that a reading wavering around one threshold doesn't flicker.
+pub struct ClutchDetector {
+    hold: Duration,
+    on_at: u8,
+    off_below: u8,
+    squeezed_since: Option<Instant>,
+    latched: bool,
+}
+
+impl ClutchDetector {
+    pub fn new(config: &Configuration) -> Self {
+        let mut detector = Self {
+            hold: Duration::ZERO,
+            on_at: 0,
+            off_below: 0,
+            squeezed_since: None,
+            latched: false,
+        };
+        detector.configure(config);
+        detector
+    }
+
+    pub fn configure(&mut self, config: &Configuration) {
+        self.hold = Duration::from_millis(config.clutch_ms.into());
+        self.on_at = config.clutch_on;
+        self.off_below = config.clutch_off;
+    }
+
+    /// Records a reading and returns whether the clutch is latched.
+    pub fn update(&mut self, flex: u8, now: Instant) -> bool {
+        if flex == 0 || flex < self.off_below {
+            self.latched = false;
+        }
+        if flex != 0 && flex >= self.on_at {
+            let since = *self.squeezed_since.get_or_insert(now);
+            if now.duration_since(since) >= self.hold {
+                self.latched = true;
+            }
+        } else {
+            self.squeezed_since = None;
+        }
+        self.latched
+    }
+
+    /// Whether the Ring-Con is squeezed but the clutch hasn't latched yet.
+    pub fn is_waiting(&self) -> bool {
+        self.squeezed_since.is_some() && !self.latched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{ClenchDetector, ClutchDetector};
+    use crate::messages::{ClenchSettings, Configuration};
+
+    fn settings() -> ClenchSettings {
+        ClenchSettings {
+            min_delta: 6,
+            window_ms: 200,
+            cooldown_ms: 500,
+            pulse_ms: 100,
+        }
+    }
+
+    fn run(detector: &mut ClenchDetector, start: Instant, readings: &[(u64, u8)]) -> Vec<bool> {
+        readings
+            .iter()
+            .map(|&(ms, flex)| detector.update(flex, start + Duration::from_millis(ms)))
+            .collect()
+    }
+
+    #[test]
+    fn quick_squeeze_pulses() {
+        let start = Instant::now();
+        let mut detector = ClenchDetector::new(settings());
+        let pulses = run(
+            &mut detector,
+            start,
+            &[(0, 15), (100, 18), (150, 21), (200, 21), (300, 21)],
+        );
+        assert_eq!(pulses, [false, false, true, true, false]);
+    }
+
+    #[test]
+    fn slow_squeeze_is_ignored() {
+        let start = Instant::now();
+        let mut detector = ClenchDetector::new(settings());
+        let pulses = run(
+            &mut detector,
+            start,
+            &[(0, 15), (150, 18), (300, 21), (450, 24)],
+        );
+        assert_eq!(pulses, [false; 4]);
+    }
+
+    #[test]
+    fn cooldown() {
+        let start = Instant::now();
+        let mut detector = ClenchDetector::new(settings());
+        let pulses = run(
+            &mut detector,
+            start,
+            &[
+                (0, 15),
+                (50, 21),
+                (200, 15),
+                (250, 21),
+                (700, 15),
+                (750, 21),
+            ],
+        );
+        assert_eq!(pulses, [false, true, false, false, false, true]);
+    }
+
+    #[test]
+    fn clutch_latches_with_hysteresis() {
+        let start = Instant::now();
+        let mut detector = ClutchDetector::new(&Configuration {
+            clutch_ms: 500,
+            clutch_on: 22,
+            clutch_off: 19,
+            ..Configuration::default()
+        });
+        let latched: Vec<_> = [
+            (0, 23),
+            (300, 21),
+            (400, 23),
+            (800, 23),
+            (900, 24),
+            (1000, 20),
+            (1100, 18),
+            (1200, 23),
+        ]
+        .iter()
+        .map(|&(ms, flex)| detector.update(flex, start + Duration::from_millis(ms)))
+        .collect();
+        assert_eq!(
+            latched,
+            [false, false, false, false, true, true, false, false]
+        );
+        assert!(detector.is_waiting());
+    }
+}
@@ -1,8 +1,11 @@
-use std::{borrow::Cow, ffi::OsString, fs, os::windows::prelude::OsStringExt, path::PathBuf};
+use std::{
+    borrow::Cow, collections::VecDeque, ffi::OsString, fs, os::windows::prelude::OsStringExt,
+    path::PathBuf,
+};
 
-use fluent_bundle::{FluentBundle, FluentResource};
+use fluent_bundle::{FluentArgs, FluentBundle, FluentResource};
 use fluent_fallback::{
-    generator::{BundleGenerator, FluentBundleResult},
+    generator::{BundleGenerator, BundleIterator, FluentBundleResult},
     types::{ResourceId, ResourceType},
     Localization,
 };
@@ -13,16 +16,15 @@ use windows::{core::PWSTR, Win32::Globalization};
 pub struct BundleIter {
     locales: std::vec::IntoIter<LanguageIdentifier>,
     res_ids: Vec<ResourceId>,
+    root: PathBuf,
     path: PathBuf,
+    prefetched: VecDeque<FluentBundleResult<FluentResource>>,
 }
 
-impl Iterator for BundleIter {
-    type Item = FluentBundleResult<FluentResource>;
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl BundleIter {
+    fn load_next(&mut self) -> Option<FluentBundleResult<FluentResource>> {
         let locale = self.locales.next()?;
-        self.path.clear();
-        self.path.push("res");
+        self.path.clone_from(&self.root);
         self.path.push(locale.to_string());
 
         let mut bundle = FluentBundle::new(vec![locale]);
@@ -55,6 +57,22 @@ impl Iterator for BundleIter {
     }
 }
 
+impl Iterator for BundleIter {
+    type Item = FluentBundleResult<FluentResource>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.prefetched.pop_front().or_else(|| self.load_next())
+    }
+}
+
+impl BundleIterator for BundleIter {
+    fn prefetch_sync(&mut self) {
+        while let Some(bundle) = self.load_next() {
+            self.prefetched.push_back(bundle);
+        }
+    }
+}
+
 impl futures::Stream for BundleIter {
     type Item = FluentBundleResult<FluentResource>;
 
@@ -62,11 +80,15 @@ impl futures::Stream for BundleIter {
         self: std::pin::Pin<&mut Self>,
         _cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        unreachable!()
+        // The resources are small local files, so load them the same way as the iterator.
+        std::task::Poll::Ready(self.get_mut().next())
     }
 }
 
-pub struct Bundles;
+/// Loads the translations from a folder with one folder per locale.
+pub struct Bundles {
+    root: PathBuf,
+}
 
 impl BundleGenerator for Bundles {
     type Resource = FluentResource;
@@ -78,7 +100,9 @@ impl BundleGenerator for Bundles {
         BundleIter {
             locales,
             res_ids,
+            root: self.root.clone(),
             path: PathBuf::new(),
+            prefetched: VecDeque::new(),
         }
     }
 }
@@ -184,18 +208,46 @@ impl Resources {
         .collect();
         //let locales = vec!["ja-JP".parse().unwrap(), "ja".parse().unwrap()];
 
-        let localization = Localization::with_env(resource_files, true, locales, Bundles);
+        Self::load(PathBuf::from("res"), resource_files, locales)
+    }
+
+    fn load(
+        root: PathBuf,
+        resource_files: Vec<ResourceId>,
+        locales: Vec<LanguageIdentifier>,
+    ) -> Self {
+        // Localization caches the bundles it generates until it is told the locales changed.
+        // Read all of the files now so that rendering never has to touch the disk.
+        let mut localization =
+            Localization::with_env(resource_files, true, locales, Bundles { root });
+        localization.prefetch_sync();
 
         Self { localization }
     }
 
-    pub fn get_string<'a>(&'a self, id: &'a str) -> Cow<'a, str> {
+    /// Formats a message. A message which can't be formatted at all shows
+    /// as its id, so that a broken translation can't take the window down.
+    fn format_value<'a>(&'a self, id: &'a str, args: Option<&'a FluentArgs<'a>>) -> Cow<'a, str> {
         let mut errors = Vec::new();
-        self.localization
+        let value = self
+            .localization
             .bundles()
-            .format_value_sync(id, None, &mut errors)
-            .unwrap()
-            .unwrap_or(Cow::Borrowed(""))
+            .format_value_sync(id, args, &mut errors);
+        if !errors.is_empty() {
+            eprintln!("Could not format {}: {:?}", id, errors);
+        }
+        match value {
+            Ok(Some(value)) => value,
+            Ok(None) => Cow::Borrowed(id),
+            Err(err) => {
+                eprintln!("Could not format {}: {:?}", id, err);
+                Cow::Borrowed(id)
+            }
+        }
+    }
+
+    pub fn get_string<'a>(&'a self, id: &'a str) -> Cow<'a, str> {
+        self.format_value(id, None)
     }
 
     pub fn fonts(&self) -> Vec<String> {
@@ -205,7 +257,44 @@ impl Resources {
             .collect()
     }
 
-    pub fn bundles(&self) -> &fluent_fallback::Bundles<Bundles> {
-        self.localization.bundles()
+    /// Formats a message with arguments. Errors are logged, and whatever could
+    /// be formatted is returned anyway.
+    pub fn format(&self, id: &str, args: &FluentArgs) -> String {
+        self.format_value(id, Some(args)).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use fluent_bundle::FluentArgs;
+    use fluent_fallback::types::{ResourceId, ResourceType};
+
+    use super::Resources;
+
+    #[test]
+    fn broken_translation_does_not_panic() {
+        let root = std::env::temp_dir().join(format!("osc-ringcon-ftl-{}", std::process::id()));
+        fs::create_dir_all(root.join("en-US")).unwrap();
+        fs::write(
+            root.join("en-US").join("main.ftl"),
+            "title = OSC Ring-Con\nbroken = { -missing-term } {\nstatus-flex = Current flex: {$flex}\n",
+        )
+        .unwrap();
+
+        let resources = Resources::load(
+            root.clone(),
+            vec![ResourceId::new("main.ftl", ResourceType::Required)],
+            vec!["en-US".parse().unwrap()],
+        );
+        assert_eq!(resources.get_string("title"), "OSC Ring-Con");
+        resources.get_string("broken");
+        assert_eq!(resources.get_string("missing"), "missing");
+        let mut args = FluentArgs::new();
+        args.set("other", 1);
+        resources.format("status-flex", &args);
+
+        fs::remove_dir_all(&root).unwrap();
     }
 }
@@ -1,11 +1,10 @@
 use std::{
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
     ops::RangeInclusive,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
 use crossbeam_channel::RecvTimeoutError;
-use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use joycon_rs::{
     joycon::{
         joycon_features::JoyConFeature,
@@ -14,7 +13,115 @@ use joycon_rs::{
     prelude::*,
 };
 
-use crate::messages::{Configuration, InitializationStep, Status};
+use crate::{
+    mapping::{map_stick, needs_calibration, MappedOutput, Mapper},
+    messages::{
+        Battery, Configuration, DeviceInfo, DeviceKind, IdleTrigger, InitializationStep, Reading,
+        Status,
+    },
+    pacing::{idle_pause, should_report, should_send, track_stillness},
+};
+
+/// Receives what [`run`] produces.
+///
+/// Any `FnMut(MappedOutput)` closure is a sink which ignores status updates
+/// and never changes the configuration.
+pub trait Sink {
+    fn output(&mut self, output: MappedOutput);
+
+    fn status(&mut self, _status: Status) {}
+
+    /// Called for every report, so that output held back can be sent late.
+    fn flush(&mut self, _now: Instant) {}
+
+    /// Returns a new configuration to switch to, if there is one.
+    fn poll_config(&mut self) -> Option<Configuration> {
+        None
+    }
+}
+
+impl<F: FnMut(MappedOutput)> Sink for F {
+    fn output(&mut self, output: MappedOutput) {
+        self(output)
+    }
+}
+
+/// Standard input reports arrive at 60Hz, so going this long without one
+/// means the link is gone even if reading hasn't failed yet.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long the Ring-Con has to read as missing before setting it up again.
+const REINIT_DELAY: Duration = Duration::from_secs(2);
+/// How often to try each sub command when setting up the Ring-Con again. It
+/// may really be missing, so this can't wait forever like the first setup.
+const REINIT_REPLY_LIMIT: Limit = Limit {
+    tries: 50,
+    time: None,
+};
+/// How long a step of the first setup may take before it counts as failed.
+/// Shorter than the parent's watchdog, so that the failure is reported
+/// rather than the agent being restarted.
+pub const STEP_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+/// How long to keep sending a sub command without getting the reply it
+/// should have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Limit {
+    tries: usize,
+    time: Option<Duration>,
+}
+
+/// How hard to try each step of the first setup.
+fn first_setup_limit(step: InitializationStep) -> Limit {
+    match step {
+        // Joy-Cons answer within a few tries whether or not the Ring-Con is
+        // attached, so a controller which never does can't read it, e.g.
+        // newer or unofficial hardware.
+        InitializationStep::McuState => Limit {
+            tries: 100,
+            time: Some(STEP_TIME_LIMIT),
+        },
+        // Step4 waits for the Ring-Con to be attached. Running out of time
+        // there is reported as a missing Ring-Con and the setup starts over.
+        _ => Limit {
+            tries: usize::MAX,
+            time: Some(STEP_TIME_LIMIT),
+        },
+    }
+}
+
+/// A setup step which never got the reply it was waiting for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct StepFailed {
+    step: InitializationStep,
+    sub_command: u8,
+    attempts: usize,
+}
+
+/// Patterns for the player LED.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Led {
+    /// The fixed pattern used when `led_feedback` is off.
+    Connected,
+    /// Reading the Ring-Con.
+    Solid,
+    /// Waiting for the Ring-Con.
+    Flashing,
+    /// The Ring-Con couldn't be set up again.
+    Off,
+}
+
+impl Led {
+    fn show(self, driver: &mut SimpleJoyConDriver) -> Result<(), JoyConError> {
+        let (light_up, flash): (&[LightUp], &[Flash]) = match self {
+            Led::Connected => (&[LightUp::LED0], &[Flash::LED0]),
+            Led::Solid => (&[LightUp::LED0], &[]),
+            Led::Flashing => (&[], &[Flash::LED0]),
+            Led::Off => (&[], &[]),
+        };
+        driver.set_player_lights(light_up, flash)?;
+        Ok(())
+    }
+}
 
 trait AsSubCommandRaw: Copy {
     fn as_sub_command_raw(self) -> u8;
@@ -32,13 +139,20 @@ impl AsSubCommandRaw for u8 {
     }
 }
 
+/// Sends a sub command for `step` until `cb` accepts the reply, giving up
+/// once `limit` is reached.
 fn repeat_sub_command<S: AsSubCommandRaw, F: FnMut(&[u8; 362]) -> Option<V>, V>(
     driver: &mut SimpleJoyConDriver,
+    step: InitializationStep,
+    limit: Limit,
     sub_command: S,
     data: &[u8],
     mut cb: F,
-) -> Result<V, JoyConError> {
-    loop {
+) -> Result<Result<V, StepFailed>, JoyConError> {
+    let start = Instant::now();
+    let mut attempts = 0;
+    while attempts < limit.tries && limit.time.is_none_or(|time| start.elapsed() < time) {
+        attempts += 1;
         let data = match driver.send_sub_command_raw(sub_command.as_sub_command_raw(), data) {
             Ok(data) => data,
             Err(JoyConError::SubCommandError(_, _)) => continue,
@@ -46,312 +160,865 @@ fn repeat_sub_command<S: AsSubCommandRaw, F: FnMut(&[u8; 362]) -> Option<V>, V>(
         };
         if let SubCommandReply::Checked(data) = data {
             if let Some(value) = cb(&data) {
-                return Ok(value);
+                return Ok(Ok(value));
             }
         } else {
             unreachable!();
         }
     }
+    Ok(Err(StepFailed {
+        step,
+        sub_command: sub_command.as_sub_command_raw(),
+        attempts,
+    }))
 }
 
-struct OscOut {
-    socket: UdpSocket,
-    target: SocketAddr,
-    buffer: Vec<u8>,
-    mid_in: u8,
-    mid_out: f32,
-    factor_low: f32,
-    factor_high: f32,
-    range_out: RangeInclusive<f32>,
-    idle_out: f32,
-}
-
-impl OscOut {
-    pub fn new() -> Self {
-        Self {
-            socket: UdpSocket::bind(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)))
-                .unwrap(),
-            target: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)),
-            buffer: Vec::new(),
-            mid_in: 0,
-            mid_out: 0.75,
-            factor_low: 0.0,
-            factor_high: 0.0,
-            range_out: 0.5..=1.0,
-            idle_out: 0.0,
+impl From<JoyConDeviceType> for DeviceKind {
+    fn from(device_type: JoyConDeviceType) -> Self {
+        match device_type {
+            JoyConDeviceType::JoyConL => DeviceKind::JoyConL,
+            JoyConDeviceType::JoyConR => DeviceKind::JoyConR,
+            JoyConDeviceType::ProCon => DeviceKind::ProCon,
         }
     }
+}
 
-    pub fn configure(&mut self, config: &Configuration) {
-        self.target = config.udp_address;
-
-        self.buffer.clear();
-        // null terminated address string, padded to 4 byte boundaries,
-        // followed by type code and float.
-        self.buffer
-            .reserve(((config.osc_address.len() + 4) & !3) + 8);
-        self.buffer.extend_from_slice(config.osc_address.as_bytes());
-        self.buffer.push(0);
-        let align = ((self.buffer.len() - 1 + 4) & !3) - self.buffer.len();
-        self.buffer.extend_from_slice(&[0, 0, 0][..align]);
-        self.buffer.extend_from_slice(b",f\0\0\0\0\0\0");
-
-        self.mid_in = config.in_center;
-        let half_out = (config.out_range.end() - config.out_range.start()) / 2.0;
-        self.mid_out = config.out_range.start() + half_out;
-        self.factor_low = half_out / (config.in_range.end() - config.in_center) as f32;
-        self.factor_high = -half_out / (config.in_center - config.in_range.start()) as f32;
-        self.range_out = f32::min(*config.out_range.start(), *config.out_range.end())
-            ..=f32::max(*config.out_range.start(), *config.out_range.end());
-
-        self.idle_out = config.out_idle;
-    }
-
-    pub fn send(&mut self, flex: u8) {
-        if self.buffer.is_empty() {
-            return;
-        }
-
-        let fflex = if flex == 0 {
-            self.idle_out
-        } else if flex == self.mid_in {
-            self.mid_out
-        } else if flex < self.mid_in {
-            (self.mid_out + (self.mid_in - flex) as f32 * self.factor_low)
-                .clamp(*self.range_out.start(), *self.range_out.end())
-        } else {
-            (self.mid_out + (flex - self.mid_in) as f32 * self.factor_high)
-                .clamp(*self.range_out.start(), *self.range_out.end())
-        };
+/// Lists the connected controllers which the system can see.
+pub fn enumerate() -> Vec<DeviceInfo> {
+    let manager = JoyConManager::get_instance();
+    let devices = {
+        let lock = manager.lock().unwrap();
+        lock.managed_devices()
+    };
 
-        let range = self.buffer.len() - 4..;
-        self.buffer[range].copy_from_slice(&fflex.to_be_bytes());
-        self.socket.send_to(&self.buffer, self.target).unwrap();
+    devices
+        .iter()
+        .filter_map(|device| {
+            let (kind, serial) = {
+                let device = device.lock().unwrap();
+                if !device.is_connected() {
+                    return None;
+                }
+                (
+                    device.device_type().into(),
+                    device.serial_number().to_string(),
+                )
+            };
+            Some(DeviceInfo {
+                kind,
+                serial,
+                battery: read_battery(device),
+            })
+        })
+        .collect()
+}
 
-        println!("Flex: {}", fflex);
+fn read_battery(device: &Arc<Mutex<JoyConDevice>>) -> Option<Battery> {
+    let mut driver = SimpleJoyConDriver::new(device).ok()?;
+    match driver
+        .send_sub_command(SubCommand::RequestDeviceInfo, &[])
+        .ok()?
+    {
+        SubCommandReply::Checked(data) => Some(Battery::from_report_byte(data[2])),
+        SubCommandReply::Unchecked => None,
     }
 }
 
-pub(crate) fn joycon_main(
-    config: IpcReceiver<Configuration>,
-    status: IpcSender<Status>,
-) -> Result<(), JoyConError> {
-    let mut osc_out = OscOut::new();
+/// Splits a line like `58 04 04 12 02` into a sub command id and its data,
+/// all in hex.
+#[cfg(feature = "sub-command-console")]
+fn parse_sub_command(line: &str) -> Option<(u8, Vec<u8>)> {
+    let mut bytes = line
+        .split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).ok());
+    let sub_command = bytes.next()??;
+    let data = bytes.collect::<Option<Vec<_>>>()?;
+    Some((sub_command, data))
+}
+
+/// Waits for any Joy-Con, then sends each sub command read from `input` and
+/// prints the reply in hex, for experimenting with sub commands this program
+/// doesn't use yet.
+#[cfg(feature = "sub-command-console")]
+pub fn sub_command_console(input: impl std::io::BufRead) -> Result<(), JoyConError> {
+    /// Sub commands are retried when the reply gets lost, but not forever.
+    const REPLY_LIMIT: Limit = Limit {
+        tries: 10,
+        time: None,
+    };
+
     let manager = JoyConManager::get_instance();
     let devices = {
         let lock = manager.lock().unwrap();
         lock.new_devices()
     };
+    println!("Waiting for a Joy-Con");
+    let device = devices.recv().expect("Unexpected joycon-rs error");
+    {
+        let device = device.lock().unwrap();
+        println!(
+            "Connected to {:?} {}",
+            device.device_type(),
+            device.serial_number()
+        );
+    }
+    let mut driver = SimpleJoyConDriver::new(&device)?;
+    driver.joycon().set_blocking_mode(true)?;
 
-    status.send(Status::NotConnected).unwrap();
-
-    // Wait for a right joycon
-    loop {
-        let device = match devices.recv_timeout(Duration::from_secs(1)) {
-            Ok(device) => device,
-            Err(RecvTimeoutError::Timeout) => {
-                while let Ok(config) = config.try_recv() {
-                    osc_out.configure(&config);
-                }
-                continue;
-            }
-            Err(RecvTimeoutError::Disconnected) => panic!("Unexpected joycon-rs error"),
+    println!("Enter a sub command id followed by its data, in hex");
+    for line in input.lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((sub_command, data)) = parse_sub_command(&line) else {
+            eprintln!("Not hex bytes: {}", line.trim());
+            continue;
         };
-
-        {
-            let device = device.lock().unwrap();
-            if device.device_type() != JoyConDeviceType::JoyConR {
-                continue;
+        // The step only labels failures, which are reported here instead.
+        let reply = repeat_sub_command(
+            &mut driver,
+            InitializationStep::Configuring,
+            REPLY_LIMIT,
+            sub_command,
+            &data,
+            |reply| Some(reply.to_vec()),
+        )?;
+        match reply {
+            Ok(reply) => {
+                let hex: Vec<_> = reply.iter().map(|byte| format!("{:02x}", byte)).collect();
+                println!("{}", hex.join(" "));
             }
+            Err(StepFailed { attempts, .. }) => eprintln!("No reply after {} tries", attempts),
         }
+    }
+    Ok(())
+}
 
-        let mut driver = SimpleJoyConDriver::new(&device)?;
+/// Sets up the Ring-Con attached to a right Joy-Con, so that its flex
+/// reading shows up at byte 40 of the standard input reports.
+pub fn initialize_ringcon(driver: &mut SimpleJoyConDriver) -> Result<(), JoyConError> {
+    initialize_ringcon_with_progress(driver, |_| {})
+}
 
-        // This initialization sequence is based on ringrunnermg/Ringcon-Driver:
-        // https://github.com/ringrunnermg/Ringcon-Driver/blob/76cad33bd545d5511eee31ef238d6a30f42e72d6/Ringcon%20Driver/joycon.hpp
+/// Like [`initialize_ringcon`], reporting each step before it starts.
+pub fn initialize_ringcon_with_progress(
+    driver: &mut SimpleJoyConDriver,
+    progress: impl FnMut(InitializationStep),
+) -> Result<(), JoyConError> {
+    match set_up(driver, progress)? {
+        Ok(()) => Ok(()),
+        Err(failed) => Err(JoyConError::SubCommandError(failed.sub_command, Vec::new())),
+    }
+}
 
-        println!("step 0");
-        status
-            .send(Status::Initializing(InitializationStep::Configuring))
-            .unwrap();
+/// Like [`initialize_ringcon_with_progress`], telling which step failed.
+fn set_up(
+    driver: &mut SimpleJoyConDriver,
+    mut progress: impl FnMut(InitializationStep),
+) -> Result<Result<(), StepFailed>, JoyConError> {
+    // This initialization sequence is based on ringrunnermg/Ringcon-Driver:
+    // https://github.com/ringrunnermg/Ringcon-Driver/blob/76cad33bd545d5511eee31ef238d6a30f42e72d6/Ringcon%20Driver/joycon.hpp
 
-        driver.joycon().set_blocking_mode(true)?;
-        driver.enable_feature(JoyConFeature::Vibration)?;
-        driver.send_sub_command(SubCommand::EnableIMU, &[0x01])?;
-        driver.send_sub_command(SubCommand::SetInputReportMode, &[0x30])?;
+    println!("step 0");
+    progress(InitializationStep::Configuring);
 
-        // step 1
-        println!("step 1");
-        status
-            .send(Status::Initializing(InitializationStep::McuConfiguration0))
-            .unwrap();
-        repeat_sub_command(
-            &mut driver,
-            SubCommand::Set_NFC_IR_MCUState,
-            &[0x01],
-            |data| {
-                if data[0xd] == 0x80 && data[0xe] == 0x22 {
-                    Some(())
-                } else {
-                    None
-                }
-            },
-        )?;
+    driver.joycon().set_blocking_mode(true)?;
+    driver.enable_feature(JoyConFeature::Vibration)?;
+    driver.send_sub_command(SubCommand::EnableIMU, &[0x01])?;
+    driver.send_sub_command(SubCommand::SetInputReportMode, &[0x30])?;
 
-        // no step 2
+    initialize_mcu(driver, first_setup_limit, progress)
+}
 
-        // step 3
-        println!("step 2");
-        status
-            .send(Status::Initializing(InitializationStep::McuConfiguration1))
-            .unwrap();
-        repeat_sub_command(
-            &mut driver,
-            SubCommand::Set_NFC_IR_MCUConfiguration,
-            &[
-                0x21, 0x00, 0x03, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfa,
-            ],
-            |data| {
-                if data[0] == 0x21 && data[15] == 1 && data[22] == 3 {
-                    Some(())
-                } else {
-                    None
-                }
-            },
-        )?;
+/// Starts the MCU in the Joy-Con and sets up the Ring-Con through it. Each
+/// sub command is tried until `limit` for its step is reached; returns the
+/// step which failed, if any.
+fn initialize_mcu(
+    driver: &mut SimpleJoyConDriver,
+    limit: impl Fn(InitializationStep) -> Limit,
+    mut progress: impl FnMut(InitializationStep),
+) -> Result<Result<(), StepFailed>, JoyConError> {
+    // step 1
+    println!("step 1");
+    progress(InitializationStep::McuState);
+    if let Err(failed) = repeat_sub_command(
+        driver,
+        InitializationStep::McuState,
+        limit(InitializationStep::McuState),
+        SubCommand::Set_NFC_IR_MCUState,
+        &[0x01],
+        |data| {
+            if data[0xd] == 0x80 && data[0xe] == 0x22 {
+                Some(())
+            } else {
+                None
+            }
+        },
+    )? {
+        return Ok(Err(failed));
+    }
 
-        // no step 4
+    // no step 2
 
-        // step 5
-        println!("step 3");
-        status
-            .send(Status::Initializing(InitializationStep::McuState))
-            .unwrap();
-        repeat_sub_command(
-            &mut driver,
-            SubCommand::Set_NFC_IR_MCUConfiguration,
-            &[
-                0x21, 0x01, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xf3,
-            ],
-            |data| {
-                if data[0] == 0x21 && data[15] == 9 && data[17] == 1 {
-                    Some(())
-                } else {
-                    None
-                }
-            },
-        )?;
+    // step 3
+    println!("step 2");
+    progress(InitializationStep::McuConfiguration0);
+    if let Err(failed) = repeat_sub_command(
+        driver,
+        InitializationStep::McuConfiguration0,
+        limit(InitializationStep::McuConfiguration0),
+        SubCommand::Set_NFC_IR_MCUConfiguration,
+        &[
+            0x21, 0x00, 0x03, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfa,
+        ],
+        |data| {
+            if data[0] == 0x21 && data[15] == 1 && data[22] == 3 {
+                Some(())
+            } else {
+                None
+            }
+        },
+    )? {
+        return Ok(Err(failed));
+    }
+
+    // no step 4
+
+    // step 5
+    println!("step 3");
+    progress(InitializationStep::McuConfiguration1);
+    if let Err(failed) = repeat_sub_command(
+        driver,
+        InitializationStep::McuConfiguration1,
+        limit(InitializationStep::McuConfiguration1),
+        SubCommand::Set_NFC_IR_MCUConfiguration,
+        &[
+            0x21, 0x01, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xf3,
+        ],
+        |data| {
+            if data[0] == 0x21 && data[15] == 9 && data[17] == 1 {
+                Some(())
+            } else {
+                None
+            }
+        },
+    )? {
+        return Ok(Err(failed));
+    }
 
-        // step 6
-        println!("step 4");
-        status
-            .send(Status::Initializing(InitializationStep::Step4))
-            .unwrap();
-        repeat_sub_command(&mut driver, 0x59, &[], |data| {
+    // step 6
+    println!("step 4");
+    progress(InitializationStep::Step4);
+    if let Err(failed) = repeat_sub_command(
+        driver,
+        InitializationStep::Step4,
+        limit(InitializationStep::Step4),
+        0x59,
+        &[],
+        |data| {
             if data[0] == 0x21 && data[14] == 0x59 && data[16] == 0x20 {
                 Some(())
             } else {
                 None
             }
-        })?;
-
-        // step 7
-        println!("step 5");
-        status
-            .send(Status::Initializing(InitializationStep::Step5))
-            .unwrap();
-        driver.send_sub_command(SubCommand::EnableIMU, &[0x03])?;
-        driver.send_sub_command(SubCommand::EnableIMU, &[0x02])?;
-        driver.send_sub_command(SubCommand::EnableIMU, &[0x01])?;
-
-        repeat_sub_command(
-            &mut driver,
-            0x5c,
-            &[
-                0x06, 0x03, 0x25, 0x06, 0x00, 0x00, 0x00, 0x00, 0x1c, 0x16, 0xed, 0x34, 0x36, 0x00,
-                0x00, 0x00, 0x0a, 0x64, 0x0b, 0xe6, 0xa9, 0x22, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
-                0x00, 0x00, 0x00, 0x00, 0x90, 0xa8, 0xe1, 0x34, 0x36,
-            ],
-            |data| {
-                if data[0] == 0x21 && data[14] == 0x5c {
-                    Some(())
-                } else {
-                    None
-                }
-            },
-        )?;
+        },
+    )? {
+        return Ok(Err(failed));
+    }
 
-        // step 8
-        println!("step 6");
-        status
-            .send(Status::Initializing(InitializationStep::Step6))
-            .unwrap();
-        repeat_sub_command(&mut driver, 0x5a, &[0x04, 0x01, 0x01, 0x02], |data| {
+    // step 7
+    println!("step 5");
+    progress(InitializationStep::Step5);
+    driver.send_sub_command(SubCommand::EnableIMU, &[0x03])?;
+    driver.send_sub_command(SubCommand::EnableIMU, &[0x02])?;
+    driver.send_sub_command(SubCommand::EnableIMU, &[0x01])?;
+
+    if let Err(failed) = repeat_sub_command(
+        driver,
+        InitializationStep::Step5,
+        limit(InitializationStep::Step5),
+        0x5c,
+        &[
+            0x06, 0x03, 0x25, 0x06, 0x00, 0x00, 0x00, 0x00, 0x1c, 0x16, 0xed, 0x34, 0x36, 0x00,
+            0x00, 0x00, 0x0a, 0x64, 0x0b, 0xe6, 0xa9, 0x22, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x90, 0xa8, 0xe1, 0x34, 0x36,
+        ],
+        |data| {
+            if data[0] == 0x21 && data[14] == 0x5c {
+                Some(())
+            } else {
+                None
+            }
+        },
+    )? {
+        return Ok(Err(failed));
+    }
+
+    // step 8
+    println!("step 6");
+    progress(InitializationStep::Step6);
+    if let Err(failed) = repeat_sub_command(
+        driver,
+        InitializationStep::Step6,
+        limit(InitializationStep::Step6),
+        0x5a,
+        &[0x04, 0x01, 0x01, 0x02],
+        |data| {
             if data[0] == 0x21 && data[14] == 0x5a {
                 Some(())
             } else {
                 None
             }
-        })?;
-
-        // step 13
-        println!("step 7");
-        status
-            .send(Status::Initializing(InitializationStep::Step7))
-            .unwrap();
-        repeat_sub_command(&mut driver, 0x58, &[0x04, 0x04, 0x12, 0x02], |data| {
+        },
+    )? {
+        return Ok(Err(failed));
+    }
+
+    // step 13
+    println!("step 7");
+    progress(InitializationStep::Step7);
+    if let Err(failed) = repeat_sub_command(
+        driver,
+        InitializationStep::Step7,
+        limit(InitializationStep::Step7),
+        0x58,
+        &[0x04, 0x04, 0x12, 0x02],
+        |data| {
             if data[0] == 0x21 && data[14] == 0x58 {
                 Some(())
             } else {
                 None
             }
-        })?;
+        },
+    )? {
+        return Ok(Err(failed));
+    }
 
-        println!("initialized");
-        driver.set_player_lights(&[LightUp::LED0], &[Flash::LED0])?;
+    println!("initialized");
+    Ok(Ok(()))
+}
+
+/// Where the Ring-Con flex is in a standard input report.
+const FLEX_OFFSET: usize = 40;
+
+/// Skips past the reports which queued up after `buf`, leaving the newest in
+/// `buf`, so that a pause between reads doesn't leave the loop behind.
+fn read_latest(
+    driver: &SimpleJoyConDriver,
+    buf: &mut [u8; 362],
+    mut len: usize,
+) -> Result<usize, JoyConError> {
+    let mut next = [0u8; 362];
+    loop {
+        match driver.read_timeout(&mut next, 0)? {
+            0 => return Ok(len),
+            read => {
+                buf[..read].copy_from_slice(&next[..read]);
+                len = read;
+            }
+        }
+    }
+}
+
+/// Gets the Ring-Con flex from a standard input report, or `None` for other
+/// reports and ones which were cut short.
+fn flex_reading(data: &[u8]) -> Option<u8> {
+    if data.first() != Some(&0x30) {
+        return None;
+    }
+    data.get(FLEX_OFFSET).copied()
+}
+
+/// Where the right stick is in a standard input report.
+const STICK_OFFSET: usize = 9;
+
+/// Gets the right stick's raw 12-bit X and Y from a standard input report.
+fn stick_reading(data: &[u8]) -> Option<(u16, u16)> {
+    if data.first() != Some(&0x30) {
+        return None;
+    }
+    let bytes = data.get(STICK_OFFSET..STICK_OFFSET + 3)?;
+    let x = u16::from(bytes[0]) | (u16::from(bytes[1] & 0xF) << 8);
+    let y = u16::from(bytes[1] >> 4) | (u16::from(bytes[2]) << 4);
+    Some((x, y))
+}
+
+/// How far the stick has to move before it is sent again, on its own.
+const STICK_THRESHOLD: f32 = 0.01;
+
+/// Which right Joy-Con to use.
+enum Choice {
+    Device(Arc<Mutex<JoyConDevice>>),
+    /// Several are connected and none is preferred.
+    Ambiguous(Vec<DeviceInfo>),
+    /// None is connected, or not the preferred one.
+    Missing,
+}
+
+/// Picks the right Joy-Con with the `preferred` serial number, or the only
+/// one connected when there is no preference.
+fn choose_right_joycon(preferred: Option<&str>) -> Choice {
+    let manager = JoyConManager::get_instance();
+    let devices = {
+        let lock = manager.lock().unwrap();
+        lock.managed_devices()
+    };
+    let mut found: Vec<_> = devices
+        .into_iter()
+        .filter(|device| {
+            let device = device.lock().unwrap();
+            device.is_connected() && device.device_type() == JoyConDeviceType::JoyConR
+        })
+        .collect();
+    let serial =
+        |device: &Arc<Mutex<JoyConDevice>>| device.lock().unwrap().serial_number().to_string();
+
+    if let Some(preferred) = preferred {
+        return match found.into_iter().find(|device| serial(device) == preferred) {
+            Some(device) => Choice::Device(device),
+            None => Choice::Missing,
+        };
+    }
+    match found.len() {
+        0 => Choice::Missing,
+        1 => Choice::Device(found.remove(0)),
+        _ => Choice::Ambiguous(
+            found
+                .iter()
+                .map(|device| DeviceInfo {
+                    kind: DeviceKind::JoyConR,
+                    serial: serial(device),
+                    // Reading it would mean talking to every controller.
+                    battery: None,
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Waits for a right Joy-Con, sets up the Ring-Con and passes mapped
+/// readings to `sink` until the controller goes away.
+pub fn run(mut config: Configuration, mut sink: impl Sink) -> Result<(), JoyConError> {
+    let mut mapper = Mapper::new(&config);
+    let manager = JoyConManager::get_instance();
+    let devices = {
+        let lock = manager.lock().unwrap();
+        lock.new_devices()
+    };
+
+    sink.status(Status::NotConnected);
+
+    // Wait for a right joycon
+    let mut idle_sent: Option<Instant> = None;
+    // Several right Joy-Cons were found, so keep checking whether one has
+    // been chosen.
+    let mut choosing = false;
+    // The last Joy-Con couldn't be set up, so keep trying it.
+    let mut failing = false;
+    loop {
+        let idle_resend = config
+            .idle_resend_ms
+            .map(|ms| Duration::from_millis(ms.into()));
+        let timeout = idle_resend.map_or(Duration::from_secs(1), |d| d.min(Duration::from_secs(1)));
+        let device = match devices.recv_timeout(timeout) {
+            Ok(device) => Some(device),
+            Err(RecvTimeoutError::Timeout) => {
+                while let Some(new_config) = sink.poll_config() {
+                    mapper.configure(&new_config);
+                    config = new_config;
+                }
+                if let Some(interval) = idle_resend {
+                    let now = Instant::now();
+                    if idle_sent.is_none_or(|sent| now.duration_since(sent) >= interval) {
+                        sink.output(MappedOutput::idle(&config));
+                        idle_sent = Some(now);
+                    }
+                }
+                if !choosing && !failing {
+                    continue;
+                }
+                None
+            }
+            Err(RecvTimeoutError::Disconnected) => panic!("Unexpected joycon-rs error"),
+        };
+
+        if let Some(device) = device {
+            let device = device.lock().unwrap();
+            let device_type = device.device_type();
+            if device_type != JoyConDeviceType::JoyConR {
+                // The Ring-Con only attaches to the right Joy-Con's rail.
+                eprintln!(
+                    "Ignoring {:?} {}, the Ring-Con needs a right Joy-Con",
+                    device_type,
+                    device.serial_number()
+                );
+                sink.status(Status::UnsupportedDevice(device_type.into()));
+                continue;
+            }
+        }
+
+        let device = match choose_right_joycon(config.preferred_serial.as_deref()) {
+            Choice::Device(device) => device,
+            Choice::Ambiguous(found) => {
+                if !choosing {
+                    eprintln!(
+                        "{} right Joy-Cons connected, waiting for a choice",
+                        found.len()
+                    );
+                    sink.status(Status::MultipleDevices(found));
+                    choosing = true;
+                }
+                continue;
+            }
+            Choice::Missing => {
+                // A new device shows up on its own once it connects again.
+                failing = false;
+                eprintln!(
+                    "Waiting for Joy-Con {}",
+                    config.preferred_serial.as_deref().unwrap_or_default()
+                );
+                continue;
+            }
+        };
+
+        let setup = SimpleJoyConDriver::new(&device).and_then(|mut driver| {
+            let result = set_up(&mut driver, |step| {
+                sink.status(Status::Initializing(step));
+            })?;
+            if result.is_ok() {
+                Led::Connected.show(&mut driver)?;
+            }
+            Ok(result.map(|()| driver))
+        });
+        let mut driver = match setup {
+            Ok(Ok(driver)) => driver,
+            Ok(Err(failed)) if failed.step == InitializationStep::McuState => {
+                eprintln!("The Joy-Con's MCU didn't start, waiting for another controller");
+                sink.status(Status::UnsupportedFirmware);
+                failing = false;
+                continue;
+            }
+            Ok(Err(failed)) if failed.step == InitializationStep::Step4 => {
+                eprintln!("No Ring-Con detected, trying again");
+                sink.status(Status::NoRingCon);
+                failing = true;
+                continue;
+            }
+            Ok(Err(failed)) => {
+                let detail = format!(
+                    "Setup step {:?} got no reply after {} tries",
+                    failed.step, failed.attempts
+                );
+                eprintln!("{}", detail);
+                sink.status(Status::HardwareError(detail));
+                failing = true;
+                continue;
+            }
+            Err(error) => match hardware_error(&error) {
+                Some(detail) => {
+                    eprintln!("Could not set up the Joy-Con: {}", detail);
+                    sink.status(Status::HardwareError(detail));
+                    failing = true;
+                    continue;
+                }
+                None => return Err(error),
+            },
+        };
+        let mut shown_led = Led::Connected;
 
         let mut last_update: Option<(u8, Instant)> = None;
-        const MAX_INTERVAL: Duration = Duration::from_secs(1);
+        let mut last_sent: Option<(u8, Instant)> = None;
+        let mut last_stick: Option<(f32, f32)> = None;
+        // Where the flex has stayed and since when, for `auto_pause_s`.
+        let mut still: Option<(u8, Instant)> = None;
+        let mut auto_paused = false;
+        let mut missing_since: Option<Instant> = None;
+        let mut last_report = Instant::now();
+        let mut observed: Option<RangeInclusive<u8>> = None;
+        let mut reinit_attempts = config.reinit_attempts;
+        let mut reinit_failed = false;
+        let mut warmup = config.warmup_reports;
+        // Until the Ring-Con is first seen, it isn't reported missing before this.
+        let mut grace_until =
+            Instant::now() + Duration::from_millis(config.ringcon_grace_ms.into());
+        // The last report was followed by an idle pause, so reports queued up.
+        let mut paused = false;
         loop {
             let mut buf = [0u8; 362];
-            let len = match driver.read(&mut buf) {
-                Ok(len) => len,
-                Err(error) => {
-                    // Send a zero to indicate the controller is gone.
-                    osc_out.send(0);
-                    status.send(Status::Disconnected).unwrap();
-                    eprintln!("{:?}", error);
-                    return Err(error);
+            let timeout = REPORT_TIMEOUT.as_millis() as i32;
+            let read = driver.read_timeout(&mut buf, timeout).and_then(|len| {
+                if paused {
+                    read_latest(&driver, &mut buf, len)
+                } else {
+                    Ok(len)
                 }
+            });
+            let len = match read {
+                Ok(len) => len,
+                Err(error) => return Err(disconnected(&mut mapper, &mut sink, error)),
             };
             let data = &buf[..len];
+            let now = Instant::now();
+            sink.flush(now);
 
-            if data[0] != 0x30 || data.len() < 40 {
+            let Some(flex) = flex_reading(data) else {
+                // Idle polling sleeps between reports, so allow for that too.
+                let idle_interval = config.idle_poll_ms.unwrap_or(0);
+                let stale_after = REPORT_TIMEOUT + Duration::from_millis(idle_interval.into());
+                if now.duration_since(last_report) > stale_after {
+                    let error = JoyConError::Disconnected;
+                    return Err(disconnected(&mut mapper, &mut sink, error));
+                }
                 continue;
+            };
+            last_report = now;
+
+            // The Joy-Con turns itself off once the battery level reads empty.
+            if data[2] >> 4 == 0 {
+                let error = JoyConError::Disconnected;
+                return Err(disconnected(&mut mapper, &mut sink, error));
             }
 
-            let flex = data[40];
-            let now = Instant::now();
-            if let Some(last_update) = last_update {
-                if last_update.0 == flex && now.duration_since(last_update.1) < MAX_INTERVAL {
-                    continue;
+            if warmup > 0 {
+                warmup -= 1;
+                continue;
+            }
+
+            if flex != 0 {
+                observed = Some(match observed {
+                    Some(range) => flex.min(*range.start())..=flex.max(*range.end()),
+                    None => flex..=flex,
+                });
+            }
+
+            missing_since = if flex == 0 {
+                Some(missing_since.unwrap_or(now))
+            } else {
+                reinit_attempts = config.reinit_attempts;
+                reinit_failed = false;
+                None
+            };
+
+            if reinit_attempts > 0
+                && missing_since.is_some_and(|since| now.duration_since(since) >= REINIT_DELAY)
+            {
+                reinit_attempts -= 1;
+                eprintln!("Ring-Con missing, setting it up again");
+                let result = initialize_mcu(
+                    &mut driver,
+                    |_| REINIT_REPLY_LIMIT,
+                    |step| {
+                        sink.status(Status::Initializing(step));
+                    },
+                );
+                match result {
+                    Ok(Ok(())) => {
+                        eprintln!("Ring-Con found");
+                        reinit_failed = false;
+                    }
+                    Ok(Err(failed)) => {
+                        eprintln!(
+                            "Ring-Con not found, step {:?} got no reply after {} tries",
+                            failed.step, failed.attempts
+                        );
+                        reinit_failed = true;
+                    }
+                    Err(error) => return Err(disconnected(&mut mapper, &mut sink, error)),
                 }
+                missing_since = None;
+                warmup = config.warmup_reports;
+                last_report = Instant::now();
+                grace_until = last_report + Duration::from_millis(config.ringcon_grace_ms.into());
+                last_update = None;
+                last_sent = None;
+                still = None;
+                continue;
+            }
+
+            let (still_at, still_since) = track_stillness(still, flex, now);
+            still = Some((still_at, still_since));
+            let pause_after = config.auto_pause_s.map(|s| Duration::from_secs(s.into()));
+            let was_paused = auto_paused;
+            auto_paused = flex != 0
+                && pause_after.is_some_and(|after| now.duration_since(still_since) >= after);
+            if auto_paused != was_paused {
+                if auto_paused {
+                    eprintln!("Ring-Con set down, pausing");
+                } else {
+                    eprintln!("Ring-Con moved, resuming");
+                }
+            }
+
+            let idle_interval = config
+                .idle_poll_ms
+                .map(|ms| Duration::from_millis(ms.into()));
+            let pause = idle_pause(missing_since, now, idle_interval)
+                .or(idle_interval.filter(|_| auto_paused));
+            // Only the reading slows down, the Joy-Con keeps reporting at its own rate.
+            paused = pause.is_some();
+            if let Some(pause) = pause {
+                std::thread::sleep(pause);
+            }
+
+            let stick = config
+                .stick
+                .as_ref()
+                .and_then(|settings| stick_reading(data).map(|raw| map_stick(raw, settings)));
+            let stick_moved = stick.is_some_and(|(x, y)| {
+                last_stick.is_none_or(|(last_x, last_y)| {
+                    (x - last_x).abs() > STICK_THRESHOLD || (y - last_y).abs() > STICK_THRESHOLD
+                })
+            });
+            let speed_changed = mapper.track_speed(flex, now);
+            let send = !auto_paused
+                && (should_send(config.resend, last_sent, flex, now)
+                    || stick_moved
+                    || speed_changed
+                    || mapper.is_settling()
+                    || mapper.is_pulsing());
+            if !send && !should_report(last_update, flex, now) {
+                continue;
             }
             last_update = Some((flex, now));
+            if send {
+                last_sent = Some((flex, now));
+                last_stick = stick;
+            }
 
-            if let Ok(conf) = config.try_recv() {
-                osc_out.configure(&conf);
+            if let Some(new_config) = sink.poll_config() {
+                mapper.configure(&new_config);
+                config = new_config;
             }
 
-            osc_out.send(flex);
+            let calibration_needed = flex != 0
+                && observed
+                    .as_ref()
+                    .is_some_and(|observed| needs_calibration(observed, &config.in_range));
+            // Nothing is sent instead of the idle value, so the last reading stays.
+            let hold_last = flex == 0 && config.idle_on == IdleTrigger::Disconnected;
+            let mut last_output = None;
+            mapper.map(flex, now, |output| {
+                let output = MappedOutput {
+                    calibration_needed: calibration_needed && !output.is_idle(),
+                    stick,
+                    ..output
+                };
+                last_output = Some(output);
+                if send && !hold_last {
+                    sink.output(output);
+                }
+            });
 
-            if flex == 0 {
-                status.send(Status::NoRingCon).unwrap();
+            let active = flex != 0 && observed.is_some() && last_output.is_some();
+            let led = if !config.led_feedback {
+                Led::Connected
+            } else if active {
+                Led::Solid
+            } else if reinit_failed {
+                Led::Off
             } else {
-                status.send(Status::Active(flex)).unwrap();
+                Led::Flashing
+            };
+            if led != shown_led {
+                if let Err(error) = led.show(&mut driver) {
+                    return Err(disconnected(&mut mapper, &mut sink, error));
+                }
+                shown_led = led;
             }
+
+            match (&observed, last_output) {
+                (Some(observed), Some(output)) if flex != 0 => {
+                    sink.status(Status::Active(Reading {
+                        flex,
+                        observed: observed.clone(),
+                        value: output.value,
+                        speed: output.speed,
+                        battery: Battery::from_report_byte(data[2]),
+                        calibration_needed,
+                    }))
+                }
+                // Still setting up, as far as the UI is concerned.
+                _ if observed.is_none() && now < grace_until => {}
+                _ => sink.status(Status::NoRingCon),
+            }
+        }
+    }
+}
+
+/// Describes errors from the system's HID layer, such as another program
+/// holding the Joy-Con, which are worth showing rather than restarting over.
+fn hardware_error(error: &JoyConError) -> Option<String> {
+    match error {
+        JoyConError::HidApiError(error) => Some(error.to_string()),
+        _ => None,
+    }
+}
+
+/// Sends the idle value and status to show that the controller is gone.
+fn disconnected(mapper: &mut Mapper, sink: &mut impl Sink, error: JoyConError) -> JoyConError {
+    mapper.map(0, Instant::now(), |output| sink.output(output));
+    sink.status(Status::Disconnected);
+    eprintln!("{:?}", error);
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        first_setup_limit, flex_reading, stick_reading, FLEX_OFFSET, STEP_TIME_LIMIT, STICK_OFFSET,
+    };
+    use crate::messages::InitializationStep;
+
+    #[test]
+    fn every_step_gives_up_in_time() {
+        for step in InitializationStep::ALL {
+            assert_eq!(
+                first_setup_limit(step).time,
+                Some(STEP_TIME_LIMIT),
+                "{:?}",
+                step
+            );
         }
     }
+
+    #[test]
+    fn flex_reading_bounds() {
+        let mut report = vec![0u8; FLEX_OFFSET + 1];
+        report[0] = 0x30;
+        report[FLEX_OFFSET] = 15;
+        assert_eq!(flex_reading(&report), Some(15));
+        assert_eq!(flex_reading(&report[..FLEX_OFFSET]), None);
+        assert_eq!(flex_reading(&report[..1]), None);
+        assert_eq!(flex_reading(&[]), None);
+
+        report[0] = 0x21;
+        assert_eq!(flex_reading(&report), None);
+    }
+
+    #[test]
+    fn stick_reading_unpacks_nibbles() {
+        let mut report = vec![0u8; STICK_OFFSET + 3];
+        report[0] = 0x30;
+        report[STICK_OFFSET..].copy_from_slice(&[0x34, 0x12, 0xAB]);
+        assert_eq!(stick_reading(&report), Some((0x234, 0xAB1)));
+        assert_eq!(stick_reading(&report[..STICK_OFFSET + 2]), None);
+
+        report[0] = 0x21;
+        assert_eq!(stick_reading(&report), None);
+    }
+
+    #[cfg(feature = "sub-command-console")]
+    #[test]
+    fn parse_sub_command() {
+        assert_eq!(
+            super::parse_sub_command("58 04 04 12 02"),
+            Some((0x58, vec![0x04, 0x04, 0x12, 0x02]))
+        );
+        assert_eq!(super::parse_sub_command(" 2 "), Some((0x02, vec![])));
+        assert_eq!(super::parse_sub_command(""), None);
+        assert_eq!(super::parse_sub_command("58 zz"), None);
+        assert_eq!(super::parse_sub_command("100"), None);
+    }
 }
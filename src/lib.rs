@@ -0,0 +1,17 @@
+//! Reads the flex sensor of a Ring-Con attached to a right Joy-Con and maps
+//! it to a value, which the `osc-ringcon` application sends over OSC.
+//!
+//! [`joycon::run`] drives the controller and hands each [`mapping::MappedOutput`]
+//! to a caller supplied sink, so the readings can also be used in-process.
+//!
+//! [`joycon::initialize_ringcon`] can be used on its own to put a Ring-Con
+//! into the mode where its flex sensor is reported.
+
+pub mod gesture;
+pub mod joycon;
+pub mod mapping;
+pub mod messages;
+pub mod osc;
+mod pacing;
+pub mod recording;
+pub mod speed;
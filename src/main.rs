@@ -1,69 +1,503 @@
 #![windows_subsystem = "windows"]
 
+use curve_editor::CurveEdit;
+use event_log::{ClockTime, EventLog};
 use fluent_bundle::FluentArgs;
 use font_kit::source::SystemSource;
-use futures::channel::mpsc;
+use futures::{channel::mpsc, Stream};
 use iced::window::Icon;
 use iced::{
-    executor, Application, Column, Command, Container, Element, Length, ProgressBar, Settings,
-    Subscription, Text,
+    button, executor, pick_list, slider, text_input, tooltip, Alignment, Application, Button,
+    Checkbox, Column, Command, Container, Element, Length, PickList, ProgressBar, Radio, Row,
+    Settings, Slider, Subscription, Text, TextInput, Tooltip,
 };
-use iced_native::subscription;
+use iced_native::{event, keyboard, subscription, window, Event};
 use image::io::Reader as ImageReader;
 use image::ImageFormat;
 use internationalization::Resources;
-use messages::{Configuration, Status};
+use osc_ringcon::messages::{
+    Battery, Configuration, DisplayUnit, Reading, Status, SMOOTHING_TAU_RANGE, UI_SCALE_RANGE,
+};
+use osc_ringcon::{joycon, osc};
+use sound::Cue;
 use std::any::TypeId;
+use std::fmt;
 use std::io::Cursor;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use style::Tone;
 use tokio::sync::watch;
 use tokio_stream::wrappers::WatchStream;
+use windows::Win32::System::Console;
 
 mod agent;
+mod canvas;
+mod config;
+mod curve_editor;
+mod discovery;
+mod event_log;
+mod firewall;
+mod gauge;
 mod internationalization;
-mod joycon;
-mod messages;
+mod metrics;
+mod playback;
+mod self_test;
+mod service;
+mod sharing;
+mod sound;
+mod sparkline;
+mod style;
+mod units;
+mod wizard;
+
+const WINDOW_SIZE: (u32, u32) = (384, 500);
+/// Size of the window in mini mode, which only shows the status.
+const MINI_WINDOW_SIZE: (u32, u32) = (384, 56);
+/// How often to check whether VRChat is listening somewhere else.
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(10);
+/// Shortest time between redraws for new readings with `low_power_ui`.
+const LOW_POWER_INTERVAL: Duration = Duration::from_millis(250);
 
 struct App {
     resources: Resources,
     status: Status,
     current_config: Configuration,
-    _config_tx: mpsc::Sender<Configuration>,
-    status_rx: watch::Receiver<Status>,
+    /// The agent, once it has been started.
+    config_tx: Option<mpsc::Sender<Configuration>>,
+    status_rx: Option<watch::Receiver<Status>>,
+    osc_in_rx: Option<watch::Receiver<Option<String>>>,
+    /// Why the agent couldn't listen for avatar changes, if it couldn't.
+    osc_in_error: Option<String>,
+    status_json: bool,
+    start_button: button::State,
+    /// One for each Joy-Con offered when several are connected.
+    device_buttons: Vec<button::State>,
+    /// Range of flex seen since launch, across agent restarts.
+    observed: Option<RangeInclusive<u8>>,
+    /// Recent flex readings for the sparkline.
+    history: sparkline::History,
+    /// Recent changes of the connection state.
+    event_log: EventLog,
+    show_log: bool,
+    log_button: button::State,
+    use_observed_button: button::State,
+    mini_mode_button: button::State,
+    copy_button: button::State,
+    reset_button: button::State,
+    paste_button: button::State,
+    /// The last paste wasn't usable settings.
+    paste_failed: bool,
+    /// The configuration from before the calibration was replaced, until
+    /// something else changes.
+    undo_config: Option<Configuration>,
+    undo_button: button::State,
+    font_choices: Vec<FontChoice>,
+    font_list: pick_list::State<FontChoice>,
+    scale_slider: slider::State,
+    attack_slider: slider::State,
+    release_slider: slider::State,
+    /// The curve point being dragged.
+    curve_grabbed: Option<usize>,
+    straight_curve_button: button::State,
+    osc_address_input: text_input::State,
+    /// The address being typed, when it isn't applied until asked.
+    draft_osc_address: Option<String>,
+    apply_button: button::State,
+    check_send_button: button::State,
+    /// Result of the last press of the connection test button.
+    send_check: Option<firewall::SendCheck>,
+    /// What the startup self test found, until it is dismissed.
+    problems: Vec<self_test::Problem>,
+    dismiss_problems_button: button::State,
+    /// The first start setup, until it is finished or skipped.
+    wizard: Option<wizard::Step>,
+    wizard_next_button: button::State,
+    wizard_skip_button: button::State,
+    find_vrchat_button: button::State,
+    should_exit: bool,
 }
 
-#[derive(Debug)]
+struct Flags {
+    resources: Resources,
+    config: Configuration,
+    status_json: bool,
+    problems: Vec<self_test::Problem>,
+    /// No settings were saved before, so the setup is shown.
+    first_run: bool,
+}
+
+#[derive(Debug, Clone)]
 enum Message {
     Status(Status),
+    OscInError(Option<String>),
+    FontSelected(FontChoice),
+    ScaleChanged(f32),
+    ScaleReleased,
+    AttackChanged(f32),
+    ReleaseChanged(f32),
+    SmoothingReleased,
+    CurveEdited(CurveEdit),
+    StraightCurve,
+    OscAddressChanged(String),
+    ApplyOscAddress,
+    LiveApplyToggled(bool),
+    LowPowerToggled(bool),
+    AutoStartToggled(bool),
+    StartAgent,
+    DeviceChosen(String),
+    AudioCuesToggled(bool),
+    CheckSend,
+    DiscoverPort,
+    PortDiscovered(SocketAddr, Option<SocketAddr>),
+    UseObserved,
+    Undo,
+    DisplayUnitSelected(DisplayUnit),
+    ToggleMiniMode,
+    ToggleLog,
+    PanicReset,
+    CopyConfig,
+    PasteConfig,
+    ConfigPasted(Option<String>),
+    WindowResized(u32, u32),
+    DismissProblems,
+    WizardNext,
+    SkipWizard,
+    CloseRequested,
+}
+
+impl App {
+    /// Starts the agent with the current configuration, if it isn't running.
+    fn start_agent(&mut self) {
+        if self.config_tx.is_some() {
+            return;
+        }
+        let (mut config_tx, status_rx, osc_in_rx) = agent::spawn(self.status_json);
+        config_tx.try_send(self.current_config.clone()).unwrap();
+        self.config_tx = Some(config_tx);
+        self.status_rx = Some(status_rx);
+        self.osc_in_rx = Some(osc_in_rx);
+    }
+
+    fn apply_config(&mut self) {
+        self.undo_config = None;
+        self.send_config();
+        if let Err(err) = config::save(&self.current_config) {
+            eprintln!("Could not save configuration {:?}", err);
+        }
+    }
+
+    /// Sends the current configuration to the agent without saving it, for
+    /// changes which are still being made.
+    fn send_config(&mut self) {
+        if let Some(config_tx) = &mut self.config_tx {
+            if let Err(err) = config_tx.try_send(self.current_config.clone()) {
+                eprintln!("Could not send configuration {:?}", err);
+            }
+        }
+    }
+
+    /// The observed range, if it differs from the configured one and can
+    /// replace it.
+    fn usable_observed(&self) -> Option<&RangeInclusive<u8>> {
+        let observed = self.observed.as_ref()?;
+        // The center has to stay strictly inside the range for the mapping to work.
+        let center = self.current_config.in_center;
+        if *observed.start() < center
+            && center < *observed.end()
+            && *observed != self.current_config.in_range
+        {
+            Some(observed)
+        } else {
+            None
+        }
+    }
+
+    /// What the startup self test found, in place of a window which would be
+    /// blank or unreadable.
+    fn problems_view(&mut self) -> Element<'_, Message> {
+        let column = self
+            .problems
+            .iter()
+            .fold(Column::new().spacing(20), |column, problem| {
+                column.push(Text::new(problem.message()).color(Tone::Bad.color()))
+            })
+            .push(
+                Button::new(
+                    &mut self.dismiss_problems_button,
+                    Text::new("Continue anyway").size(14),
+                )
+                .on_press(Message::DismissProblems),
+            );
+        Container::new(column)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .center_y()
+            .padding(20)
+            .into()
+    }
+
+    /// Whether the observed range has the center inside it, so that it can
+    /// be used as the input range.
+    fn is_calibrated(&self) -> bool {
+        let center = self.current_config.in_center;
+        self.observed
+            .as_ref()
+            .is_some_and(|observed| *observed.start() < center && center < *observed.end())
+    }
+
+    /// One step of the first start setup at a time.
+    fn wizard_view(&mut self, step: wizard::Step) -> Element<'_, Message> {
+        let tone = Tone::of(&self.status);
+        // Worked out before the buttons borrow their states.
+        let done = step.is_done(&self.status, self.is_calibrated());
+        let mut column = Column::new()
+            .spacing(20)
+            .push(Text::new(self.resources.get_string("wizard-title").into_owned()).size(24))
+            .push(Text::new(
+                self.resources.get_string(step.help_key()).into_owned(),
+            ));
+        match step {
+            wizard::Step::Connect => {
+                let status = self
+                    .resources
+                    .get_string(event_log::label_key(&self.status))
+                    .into_owned();
+                column = column.push(Text::new(status).color(tone.color()));
+            }
+            wizard::Step::Port => {
+                let mut args = FluentArgs::new();
+                args.set("address", self.current_config.udp_address.to_string());
+                column = column
+                    .push(Text::new(self.resources.format("wizard-address", &args)).size(14))
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .push(
+                                Button::new(
+                                    &mut self.find_vrchat_button,
+                                    Text::new(
+                                        self.resources.get_string("wizard-find").into_owned(),
+                                    )
+                                    .size(14),
+                                )
+                                .on_press(Message::DiscoverPort),
+                            )
+                            .push(
+                                Button::new(
+                                    &mut self.check_send_button,
+                                    Text::new(self.resources.get_string("check-send").into_owned())
+                                        .size(14),
+                                )
+                                .on_press(Message::CheckSend),
+                            ),
+                    );
+                if let Some(check) = self.send_check {
+                    column = column.push(
+                        Text::new(self.resources.get_string(check.label_key()).into_owned())
+                            .size(14),
+                    );
+                }
+            }
+            wizard::Step::Calibrate => {
+                if let Some(observed) = &self.observed {
+                    let mut args = FluentArgs::new();
+                    args.set("min", *observed.start());
+                    args.set("max", *observed.end());
+                    let text = self.resources.format("observed", &args);
+                    column = column.push(Text::new(text).size(14));
+                }
+            }
+        }
+
+        let next_key = if step.next().is_some() {
+            "wizard-next"
+        } else {
+            "wizard-finish"
+        };
+        let mut next = Button::new(
+            &mut self.wizard_next_button,
+            Text::new(self.resources.get_string(next_key).into_owned()).size(14),
+        );
+        if done {
+            next = next.on_press(Message::WizardNext);
+        }
+        column = column.push(
+            Row::new().spacing(10).push(next).push(
+                Button::new(
+                    &mut self.wizard_skip_button,
+                    Text::new(self.resources.get_string("wizard-skip").into_owned()).size(14),
+                )
+                .on_press(Message::SkipWizard),
+            ),
+        );
+        Container::new(column)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(20)
+            .into()
+    }
+
+    /// The main line of status while reading, as the raw flex or, following
+    /// `display_unit`, as a percentage of the input range with the value sent.
+    fn flex_text(&self, reading: &Reading) -> String {
+        let in_range = &self.current_config.in_range;
+        let unit = self.current_config.display_unit;
+        let mut args = FluentArgs::new();
+        args.set("min", *in_range.start());
+        args.set("flex", reading.flex);
+        args.set("max", *in_range.end());
+        args.set(
+            "percent",
+            units::format_input(reading.flex, DisplayUnit::Percent, Some(in_range)),
+        );
+        args.set("value", units::format_output(reading.value, unit));
+        let key = match unit {
+            DisplayUnit::Raw => "status-flex",
+            DisplayUnit::Percent => "status-flex-percent",
+        };
+        self.resources.format(key, &args)
+    }
+
+    /// A single line of status, for keeping the window visible during play.
+    fn mini_view(&mut self) -> Element<'_, Message> {
+        let tone = Tone::of(&self.status);
+        let text = match &self.status {
+            _ if self.config_tx.is_none() => self.resources.get_string("not-started").into_owned(),
+            Status::NotConnected => self.resources.get_string("connect-joycon").into_owned(),
+            Status::Initializing(step) => self.resources.get_string(step.label_key()).into_owned(),
+            Status::NoRingCon => self.resources.get_string("connect-ringcon").into_owned(),
+            Status::Active(reading) => self.flex_text(reading),
+            Status::Disconnected => self.resources.get_string("restarting").into_owned(),
+            Status::UnsupportedDevice(kind) => self
+                .resources
+                .get_string(kind.unsupported_key())
+                .into_owned(),
+            Status::MultipleDevices(_) => self.resources.get_string("choose-joycon").into_owned(),
+            Status::HardwareError(_) => self.resources.get_string("hardware-error").into_owned(),
+            Status::UnsupportedFirmware => self
+                .resources
+                .get_string("unsupported-firmware")
+                .into_owned(),
+        };
+
+        let mut row = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new(text).color(tone.color()).width(Length::Fill))
+            .push(Tooltip::new(
+                Button::new(
+                    &mut self.reset_button,
+                    Text::new(self.resources.get_string("panic-reset").into_owned()).size(14),
+                )
+                .style(Tone::Bad)
+                .on_press(Message::PanicReset),
+                "Ctrl+R",
+                tooltip::Position::Left,
+            ))
+            .push(Tooltip::new(
+                Button::new(
+                    &mut self.mini_mode_button,
+                    Text::new(self.resources.get_string("full-mode").into_owned()).size(14),
+                )
+                .on_press(Message::ToggleMiniMode),
+                "Ctrl+M",
+                tooltip::Position::Left,
+            ));
+        if self.config_tx.is_none() {
+            row = row.push(
+                Button::new(
+                    &mut self.start_button,
+                    Text::new(self.resources.get_string("start").into_owned()).size(14),
+                )
+                .on_press(Message::StartAgent),
+            );
+        }
+        Container::new(row)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_y()
+            .padding(10)
+            .into()
+    }
 }
 
 impl Application for App {
     type Executor = executor::Default;
     type Message = Message;
-    type Flags = Option<Resources>;
-
-    fn new(resources: Option<Resources>) -> (App, Command<Message>) {
-        let (mut config_tx, status_rx) = agent::spawn();
-        let config = Configuration {
-            udp_address: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 9000)),
-            osc_address: "/avatar/parameters/ringcon_flex".to_string(),
-            in_center: 15,
-            in_range: 7..=24,
-            out_idle: 0.0,
-            out_range: 0.5..=1.0,
+    type Flags = Option<Flags>;
+
+    fn new(flags: Option<Flags>) -> (App, Command<Message>) {
+        let Flags {
+            resources,
+            config,
+            status_json,
+            problems,
+            first_run,
+        } = flags.unwrap();
+
+        let mut font_choices = vec![FontChoice {
+            family: None,
+            label: resources.get_string("font-default").into_owned(),
+        }];
+        font_choices.extend(font_families().into_iter().map(|family| FontChoice {
+            label: family.clone(),
+            family: Some(family),
+        }));
+
+        let auto_start = config.auto_start;
+        let mut app = App {
+            status: Status::NotConnected,
+            current_config: config,
+            config_tx: None,
+            status_rx: None,
+            osc_in_rx: None,
+            osc_in_error: None,
+            status_json,
+            start_button: button::State::new(),
+            device_buttons: Vec::new(),
+            observed: None,
+            history: sparkline::History::default(),
+            event_log: EventLog::default(),
+            show_log: false,
+            log_button: button::State::new(),
+            use_observed_button: button::State::new(),
+            mini_mode_button: button::State::new(),
+            copy_button: button::State::new(),
+            reset_button: button::State::new(),
+            paste_button: button::State::new(),
+            paste_failed: false,
+            undo_config: None,
+            undo_button: button::State::new(),
+            resources,
+            font_choices,
+            font_list: pick_list::State::new(),
+            scale_slider: slider::State::new(),
+            attack_slider: slider::State::new(),
+            release_slider: slider::State::new(),
+            curve_grabbed: None,
+            straight_curve_button: button::State::new(),
+            osc_address_input: text_input::State::new(),
+            draft_osc_address: None,
+            apply_button: button::State::new(),
+            check_send_button: button::State::new(),
+            send_check: None,
+            problems,
+            dismiss_problems_button: button::State::new(),
+            wizard: first_run.then_some(wizard::Step::Connect),
+            wizard_next_button: button::State::new(),
+            wizard_skip_button: button::State::new(),
+            find_vrchat_button: button::State::new(),
+            should_exit: false,
         };
-        config_tx.try_send(config.clone()).unwrap();
-
-        (
-            App {
-                status: Status::NotConnected,
-                current_config: config,
-                _config_tx: config_tx,
-                status_rx,
-                resources: resources.unwrap(),
-            },
-            Command::none(),
-        )
+        // The setup starts by looking for the Joy-Con.
+        if auto_start || first_run {
+            app.start_agent();
+        }
+        (app, Command::none())
     }
 
     fn title(&self) -> String {
@@ -71,61 +505,745 @@ impl Application for App {
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
+        // iced doesn't say when the field loses focus, so the address typed
+        // is applied with whatever happens next.
+        if self.current_config.live_apply && !self.osc_address_input.is_focused() {
+            if let Some(address) = self.draft_osc_address.take() {
+                self.current_config.osc_address = address;
+                self.apply_config();
+            }
+        }
         match message {
             Message::Status(status) => {
+                if let Status::Active(Reading { observed, .. }) = &status {
+                    self.observed = Some(match &self.observed {
+                        Some(range) => {
+                            *range.start().min(observed.start())..=*range.end().max(observed.end())
+                        }
+                        None => observed.clone(),
+                    });
+                }
+                let flex = match &status {
+                    Status::Active(reading) => Some(reading.flex),
+                    _ => None,
+                };
+                self.history.push(flex, Instant::now());
+                self.event_log.push(&status, ClockTime::now());
+                if self.current_config.audio_cues {
+                    if let Some(cue) = Cue::for_transition(&self.status, &status) {
+                        cue.play();
+                    }
+                }
                 self.status = status;
             }
+            Message::FontSelected(choice) => {
+                self.current_config.font_family = choice.family;
+                self.apply_config();
+            }
+            Message::ScaleChanged(scale) => {
+                self.current_config.ui_scale = scale;
+            }
+            Message::ScaleReleased => {
+                self.apply_config();
+                let (width, height) = scaled_window_size(&self.current_config);
+                return iced::window::resize(width, height);
+            }
+            Message::AttackChanged(tau) => {
+                self.current_config.attack_tau = tau;
+                if self.current_config.live_apply {
+                    self.send_config();
+                }
+            }
+            Message::ReleaseChanged(tau) => {
+                self.current_config.release_tau = tau;
+                if self.current_config.live_apply {
+                    self.send_config();
+                }
+            }
+            Message::SmoothingReleased => {
+                self.apply_config();
+            }
+            Message::CurveEdited(edit) => {
+                let points = &mut self.current_config.curve_points;
+                match edit {
+                    CurveEdit::Grab(index) => self.curve_grabbed = Some(index),
+                    CurveEdit::Add(point) => {
+                        points.push(point);
+                        self.curve_grabbed = Some(points.len() - 1);
+                    }
+                    CurveEdit::Move(point) => {
+                        if let Some(grabbed) = self.curve_grabbed.and_then(|i| points.get_mut(i)) {
+                            *grabbed = point;
+                        }
+                    }
+                    CurveEdit::Remove(index) => {
+                        points.remove(index);
+                        self.curve_grabbed = None;
+                        self.apply_config();
+                    }
+                    CurveEdit::Release => {
+                        self.curve_grabbed = None;
+                        self.apply_config();
+                    }
+                }
+            }
+            Message::StraightCurve => {
+                self.current_config.curve_points.clear();
+                self.curve_grabbed = None;
+                self.apply_config();
+            }
+            Message::CheckSend => {
+                self.send_check = Some(firewall::check_send(self.current_config.udp_address));
+            }
+            // Half typed addresses aren't sent, even when applying live.
+            Message::OscAddressChanged(address) => self.draft_osc_address = Some(address),
+            Message::ApplyOscAddress => {
+                if let Some(address) = self.draft_osc_address.take() {
+                    self.current_config.osc_address = address;
+                    self.apply_config();
+                }
+            }
+            Message::LowPowerToggled(low_power_ui) => {
+                self.current_config.low_power_ui = low_power_ui;
+                self.apply_config();
+            }
+            Message::AutoStartToggled(auto_start) => {
+                self.current_config.auto_start = auto_start;
+                self.apply_config();
+            }
+            Message::StartAgent => self.start_agent(),
+            Message::DeviceChosen(serial) => {
+                self.current_config.preferred_serial = Some(serial);
+                self.apply_config();
+            }
+            Message::AudioCuesToggled(audio_cues) => {
+                self.current_config.audio_cues = audio_cues;
+                self.apply_config();
+            }
+            Message::LiveApplyToggled(live_apply) => {
+                if let Some(address) = self.draft_osc_address.take() {
+                    self.current_config.osc_address = address;
+                }
+                self.current_config.live_apply = live_apply;
+                self.apply_config();
+            }
+            Message::OscInError(error) => self.osc_in_error = error,
+            Message::DiscoverPort => {
+                let probed = self.current_config.udp_address;
+                return Command::perform(
+                    tokio::task::spawn_blocking(move || discovery::discover(probed)),
+                    move |found| Message::PortDiscovered(probed, found.ok().flatten()),
+                );
+            }
+            Message::PortDiscovered(probed, found) => {
+                // The probe takes a while; the user may have picked another
+                // address in the meantime.
+                if let Some(address) = found.filter(|_| probed == self.current_config.udp_address) {
+                    eprintln!(
+                        "Switching to {}, where VRChat seems to be listening",
+                        address
+                    );
+                    self.current_config.udp_address = address;
+                    self.apply_config();
+                }
+            }
+            Message::DisplayUnitSelected(unit) => {
+                self.current_config.display_unit = unit;
+                self.apply_config();
+            }
+            Message::UseObserved => {
+                if let Some(observed) = self.usable_observed().cloned() {
+                    let previous = self.current_config.clone();
+                    self.current_config.in_range = observed;
+                    self.apply_config();
+                    self.undo_config = Some(previous);
+                    if self.current_config.audio_cues {
+                        Cue::Calibrated.play();
+                    }
+                }
+            }
+            Message::Undo => {
+                if let Some(previous) = self.undo_config.take() {
+                    let window_size = self.current_config.window_size;
+                    self.current_config = Configuration {
+                        window_size,
+                        ..previous
+                    };
+                    self.apply_config();
+                }
+            }
+            Message::ToggleMiniMode => {
+                self.current_config.mini_mode = !self.current_config.mini_mode;
+                self.apply_config();
+                let (width, height) = match self.current_config.window_size {
+                    Some(size) if !self.current_config.mini_mode => size,
+                    _ => scaled_window_size(&self.current_config),
+                };
+                return iced::window::resize(width, height);
+            }
+            Message::ToggleLog => self.show_log = !self.show_log,
+            Message::PanicReset => {
+                // Sent from here rather than the agent, in case the agent is stuck.
+                agent::send_idle(&self.current_config);
+            }
+            Message::CopyConfig => {
+                return iced::clipboard::write(sharing::export(&self.current_config));
+            }
+            Message::PasteConfig => {
+                return iced::clipboard::read(Message::ConfigPasted);
+            }
+            Message::ConfigPasted(text) => {
+                let imported = text
+                    .ok_or_else(|| anyhow::anyhow!("The clipboard is empty"))
+                    .and_then(|text| sharing::import(&text, &self.current_config));
+                match imported {
+                    Ok(config) => {
+                        let previous = std::mem::replace(&mut self.current_config, config);
+                        self.draft_osc_address = None;
+                        self.apply_config();
+                        self.undo_config = Some(previous);
+                        self.paste_failed = false;
+                    }
+                    Err(err) => {
+                        eprintln!("Could not paste settings: {:#}", err);
+                        self.paste_failed = true;
+                    }
+                }
+            }
+            Message::WindowResized(width, height) => {
+                // Only the full window's size is worth keeping.
+                if !self.current_config.mini_mode {
+                    self.current_config.window_size = Some((width, height));
+                }
+            }
+            Message::DismissProblems => self.problems.clear(),
+            Message::WizardNext => {
+                if let Some(step) = self.wizard {
+                    if step == wizard::Step::Calibrate && self.is_calibrated() {
+                        self.current_config.in_range = self.observed.clone().unwrap();
+                    }
+                    self.wizard = step.next();
+                    if self.wizard.is_none() {
+                        self.apply_config();
+                    }
+                }
+            }
+            Message::SkipWizard => {
+                self.wizard = None;
+                self.apply_config();
+            }
+            Message::CloseRequested => {
+                if let Err(err) = config::save(&self.current_config) {
+                    eprintln!("Could not save configuration {:?}", err);
+                }
+                self.should_exit = true;
+            }
         }
         Command::none()
     }
 
-    fn view(&mut self) -> Element<Message> {
-        let mut column = Column::new().spacing(20);
+    fn view(&mut self) -> Element<'_, Message> {
+        if !self.problems.is_empty() {
+            return self.problems_view();
+        }
+        if let Some(step) = self.wizard {
+            return self.wizard_view(step);
+        }
+        if self.current_config.mini_mode {
+            return self.mini_view();
+        }
+
+        // Worked out before the widgets below borrow their states.
+        let usable_observed = self.usable_observed().is_some();
+        let flex_text = match &self.status {
+            Status::Active(reading) => Some(self.flex_text(reading)),
+            _ => None,
+        };
+
+        let mut column = Column::new().spacing(20).push(Tooltip::new(
+            Button::new(
+                &mut self.reset_button,
+                Text::new(self.resources.get_string("panic-reset").into_owned()).size(14),
+            )
+            .style(Tone::Bad)
+            .on_press(Message::PanicReset),
+            "Ctrl+R",
+            tooltip::Position::Bottom,
+        ));
+        let tone = Tone::of(&self.status);
 
         match &self.status {
+            _ if self.config_tx.is_none() => {
+                column = column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(Text::new(
+                            self.resources.get_string("not-started").into_owned(),
+                        ))
+                        .push(
+                            Button::new(
+                                &mut self.start_button,
+                                Text::new(self.resources.get_string("start").into_owned()).size(14),
+                            )
+                            .on_press(Message::StartAgent),
+                        ),
+                );
+            }
             Status::NotConnected => {
-                column = column.push(Text::new(
-                    self.resources.get_string("connect-joycon").into_owned(),
-                ));
+                column = column.push(
+                    Text::new(self.resources.get_string("connect-joycon").into_owned())
+                        .color(tone.color()),
+                );
             }
             Status::Initializing(step) => {
                 column = column
-                    .push(Text::new(
-                        self.resources
-                            .get_string("initializing-joycon")
-                            .into_owned(),
-                    ))
-                    .push(ProgressBar::new(0.0..=1.0, *step as i32 as f32 / 8.0));
+                    .push(
+                        Text::new(
+                            self.resources
+                                .get_string("initializing-joycon")
+                                .into_owned(),
+                        )
+                        .color(tone.color()),
+                    )
+                    .push(
+                        Text::new(self.resources.get_string(step.label_key()).into_owned())
+                            .size(14),
+                    )
+                    .push(ProgressBar::new(0.0..=1.0, step.progress()).style(tone));
             }
             Status::NoRingCon => {
-                column = column.push(Text::new(
-                    self.resources.get_string("connect-ringcon").into_owned(),
-                ));
+                column = column.push(
+                    Text::new(self.resources.get_string("connect-ringcon").into_owned())
+                        .color(tone.color()),
+                );
             }
-            Status::Active(flex) => {
+            Status::Active(reading) => {
+                let Reading {
+                    flex,
+                    observed,
+                    battery,
+                    calibration_needed,
+                    ..
+                } = reading;
+                let in_range = &self.current_config.in_range;
+                let text = flex_text.unwrap_or_default();
+                // Widen the bar to what was observed, so that readings past
+                // the input range aren't pinned at an end.
+                let start = *in_range.start().min(observed.start());
+                let end = *in_range.end().max(observed.end());
+                let bar_tone = if in_range.contains(flex) {
+                    tone
+                } else {
+                    Tone::Waiting
+                };
+                column = column
+                    .push(Text::new(text).color(tone.color()))
+                    .push(gauge::view(
+                        start..=end,
+                        in_range.clone(),
+                        self.current_config.in_center,
+                        *flex,
+                        bar_tone.color(),
+                    ));
+                // The status line already shows the percentage otherwise.
+                if self.current_config.display_unit == DisplayUnit::Raw {
+                    let mut args = FluentArgs::new();
+                    args.set(
+                        "percent",
+                        units::format_input(*flex, DisplayUnit::Percent, Some(in_range)),
+                    );
+                    let percent = self.resources.format("in-range-percent", &args);
+                    column = column.push(Text::new(percent).size(14));
+                }
+
                 let mut args = FluentArgs::new();
-                args.set("min", *self.current_config.in_range.start());
-                args.set("flex", *flex);
-                args.set("max", *self.current_config.in_range.end());
-                let mut errors = Vec::new();
-                let text = self
-                    .resources
-                    .bundles()
-                    .format_value_sync("status-flex", Some(&args), &mut errors)
-                    .unwrap()
-                    .map(|c| c.into_owned())
-                    .unwrap_or_default();
-                column = column.push(Text::new(text)).push(ProgressBar::new(
-                    (*self.current_config.in_range.start() as f32)
-                        ..=(*self.current_config.in_range.end() as f32),
-                    *flex as f32,
-                ));
+                args.set("percent", battery.percent);
+                let key = if battery.charging {
+                    "battery-charging"
+                } else {
+                    "battery"
+                };
+                column = column.push(Text::new(self.resources.format(key, &args)).size(14));
+                if *calibration_needed {
+                    column = column.push(
+                        Text::new(self.resources.get_string("calibration-needed").into_owned())
+                            .size(14)
+                            .color(Tone::Waiting.color()),
+                    );
+                }
             }
             Status::Disconnected => {
-                column = column.push(Text::new(
-                    self.resources.get_string("restarting").into_owned(),
+                column = column.push(
+                    Text::new(self.resources.get_string("restarting").into_owned())
+                        .color(tone.color()),
+                );
+            }
+            Status::UnsupportedDevice(kind) => {
+                column = column.push(
+                    Text::new(
+                        self.resources
+                            .get_string(kind.unsupported_key())
+                            .into_owned(),
+                    )
+                    .color(tone.color()),
+                );
+            }
+            Status::HardwareError(detail) => {
+                column = column
+                    .push(
+                        Text::new(self.resources.get_string("hardware-error").into_owned())
+                            .color(tone.color()),
+                    )
+                    .push(Text::new(detail.clone()).size(14));
+            }
+            Status::UnsupportedFirmware => {
+                column = column.push(
+                    Text::new(
+                        self.resources
+                            .get_string("unsupported-firmware")
+                            .into_owned(),
+                    )
+                    .color(tone.color()),
+                );
+            }
+            Status::MultipleDevices(devices) => {
+                column = column.push(
+                    Text::new(self.resources.get_string("choose-joycon").into_owned())
+                        .color(tone.color()),
+                );
+                self.device_buttons
+                    .resize_with(devices.len(), button::State::new);
+                for (device, state) in devices.iter().zip(&mut self.device_buttons) {
+                    column = column.push(
+                        Button::new(state, Text::new(device.serial.clone()).size(14))
+                            .on_press(Message::DeviceChosen(device.serial.clone())),
+                    );
+                }
+            }
+        }
+
+        if self.observed.is_some() {
+            column = column.push(
+                self.history
+                    .view(self.current_config.in_range.clone(), tone.color()),
+            );
+        }
+
+        if let Some(observed) = &self.observed {
+            let mut args = FluentArgs::new();
+            args.set("min", *observed.start());
+            args.set("max", *observed.end());
+            let text = self.resources.format("observed", &args);
+            let mut row = Row::new().spacing(10).push(Text::new(text).size(14));
+            if usable_observed {
+                row = row.push(Tooltip::new(
+                    Button::new(
+                        &mut self.use_observed_button,
+                        Text::new(self.resources.get_string("use-observed").into_owned()).size(14),
+                    )
+                    .on_press(Message::UseObserved),
+                    "Ctrl+K",
+                    tooltip::Position::Bottom,
                 ));
             }
+            if self.undo_config.is_some() {
+                row = row.push(Tooltip::new(
+                    Button::new(
+                        &mut self.undo_button,
+                        Text::new(self.resources.get_string("undo").into_owned()).size(14),
+                    )
+                    .on_press(Message::Undo),
+                    "Ctrl+Z",
+                    tooltip::Position::Bottom,
+                ));
+            }
+            column = column.push(row);
+        }
+
+        let unit = self.current_config.display_unit;
+        let mut args = FluentArgs::new();
+        args.set(
+            "input",
+            units::format_input_range(&self.current_config.in_range, unit, self.observed.as_ref()),
+        );
+        args.set(
+            "output",
+            units::format_output_range(&self.current_config.out_range, unit),
+        );
+        let text = self.resources.format("ranges", &args);
+        column = column.push(Text::new(text).size(14));
+        if self.current_config.out_range.start() == self.current_config.out_range.end() {
+            column = column.push(
+                Text::new(self.resources.get_string("constant-output").into_owned())
+                    .size(14)
+                    .color(Tone::Waiting.color()),
+            );
+        }
+        column = column.push(
+            [
+                (DisplayUnit::Raw, "unit-raw"),
+                (DisplayUnit::Percent, "unit-percent"),
+            ]
+            .into_iter()
+            .fold(
+                Row::new()
+                    .spacing(10)
+                    .push(Text::new(self.resources.get_string("units").into_owned())),
+                |row, (choice, label)| {
+                    row.push(
+                        Radio::new(
+                            choice,
+                            self.resources.get_string(label).into_owned(),
+                            Some(unit),
+                            Message::DisplayUnitSelected,
+                        )
+                        .size(16),
+                    )
+                },
+            ),
+        );
+
+        let osc_address = self
+            .draft_osc_address
+            .as_deref()
+            .unwrap_or(&self.current_config.osc_address);
+        let mut row = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new(
+                self.resources.get_string("osc-address").into_owned(),
+            ))
+            .push(
+                TextInput::new(
+                    &mut self.osc_address_input,
+                    "/avatar/parameters/ringcon_flex",
+                    osc_address,
+                    Message::OscAddressChanged,
+                )
+                .on_submit(Message::ApplyOscAddress),
+            );
+        if self.draft_osc_address.is_some() {
+            row = row.push(
+                Button::new(
+                    &mut self.apply_button,
+                    Text::new(self.resources.get_string("apply").into_owned()).size(14),
+                )
+                .on_press(Message::ApplyOscAddress),
+            );
+        }
+        column = column.push(row).push(
+            Checkbox::new(
+                self.current_config.live_apply,
+                self.resources.get_string("live-apply").into_owned(),
+                Message::LiveApplyToggled,
+            )
+            .size(16)
+            .text_size(14),
+        );
+        if let Some(suggestion) = osc::suggest_osc_address(osc_address) {
+            let mut args = FluentArgs::new();
+            args.set("suggestion", suggestion);
+            let text = self.resources.format("osc-address-suggestion", &args);
+            column = column.push(Text::new(text).size(14).color(Tone::Waiting.color()));
+        }
+
+        column = column.push(
+            Button::new(
+                &mut self.check_send_button,
+                Text::new(self.resources.get_string("check-send").into_owned()).size(14),
+            )
+            .on_press(Message::CheckSend),
+        );
+        if let Some(check) = self.send_check {
+            column = column.push(
+                Text::new(self.resources.get_string(check.label_key()).into_owned()).size(14),
+            );
+        }
+        if let Some(error) = &self.osc_in_error {
+            let mut args = FluentArgs::new();
+            args.set("port", self.current_config.osc_in_port);
+            args.set("error", error.as_str());
+            let text = self.resources.format("osc-in-unavailable", &args);
+            column = column.push(Text::new(text).size(14).color(Tone::Bad.color()));
+        }
+
+        let selected_font = self
+            .font_choices
+            .iter()
+            .find(|c| c.family == self.current_config.font_family)
+            .cloned();
+        column = column
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Text::new(self.resources.get_string("font").into_owned()))
+                    .push(
+                        PickList::new(
+                            &mut self.font_list,
+                            &self.font_choices[..],
+                            selected_font,
+                            Message::FontSelected,
+                        )
+                        .width(Length::Fill),
+                    ),
+            )
+            .push(Text::new(self.resources.get_string("font-restart").into_owned()).size(14))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Text::new(
+                        self.resources.get_string("ui-scale").into_owned(),
+                    ))
+                    .push(
+                        Slider::new(
+                            &mut self.scale_slider,
+                            UI_SCALE_RANGE,
+                            self.current_config.ui_scale,
+                            Message::ScaleChanged,
+                        )
+                        .step(0.25)
+                        .on_release(Message::ScaleReleased),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Text::new(self.resources.get_string("attack").into_owned()))
+                    .push(
+                        Slider::new(
+                            &mut self.attack_slider,
+                            SMOOTHING_TAU_RANGE,
+                            self.current_config.attack_tau,
+                            Message::AttackChanged,
+                        )
+                        .step(0.05)
+                        .on_release(Message::SmoothingReleased),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Text::new(self.resources.get_string("release").into_owned()))
+                    .push(
+                        Slider::new(
+                            &mut self.release_slider,
+                            SMOOTHING_TAU_RANGE,
+                            self.current_config.release_tau,
+                            Message::ReleaseChanged,
+                        )
+                        .step(0.05)
+                        .on_release(Message::SmoothingReleased),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Text::new(self.resources.get_string("curve").into_owned()))
+                    .push(curve_editor::view(
+                        &self.current_config.curve_points,
+                        self.curve_grabbed,
+                        tone.color(),
+                        Message::CurveEdited,
+                    ))
+                    .push(
+                        Button::new(
+                            &mut self.straight_curve_button,
+                            Text::new(self.resources.get_string("straight-curve").into_owned())
+                                .size(14),
+                        )
+                        .on_press(Message::StraightCurve),
+                    ),
+            )
+            .push(Text::new(self.resources.get_string("curve-help").into_owned()).size(14))
+            .push(Tooltip::new(
+                Button::new(
+                    &mut self.mini_mode_button,
+                    Text::new(self.resources.get_string("mini-mode").into_owned()).size(14),
+                )
+                .on_press(Message::ToggleMiniMode),
+                "Ctrl+M",
+                tooltip::Position::Bottom,
+            ))
+            .push(
+                Checkbox::new(
+                    self.current_config.low_power_ui,
+                    self.resources.get_string("low-power").into_owned(),
+                    Message::LowPowerToggled,
+                )
+                .size(16)
+                .text_size(14),
+            )
+            .push(
+                Checkbox::new(
+                    self.current_config.auto_start,
+                    self.resources.get_string("auto-start").into_owned(),
+                    Message::AutoStartToggled,
+                )
+                .size(16)
+                .text_size(14),
+            )
+            .push(
+                Checkbox::new(
+                    self.current_config.audio_cues,
+                    self.resources.get_string("audio-cues").into_owned(),
+                    Message::AudioCuesToggled,
+                )
+                .size(16)
+                .text_size(14),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        Button::new(
+                            &mut self.copy_button,
+                            Text::new(self.resources.get_string("copy-config").into_owned())
+                                .size(14),
+                        )
+                        .on_press(Message::CopyConfig),
+                    )
+                    .push(
+                        Button::new(
+                            &mut self.paste_button,
+                            Text::new(self.resources.get_string("paste-config").into_owned())
+                                .size(14),
+                        )
+                        .on_press(Message::PasteConfig),
+                    ),
+            );
+        if self.paste_failed {
+            column = column.push(
+                Text::new(self.resources.get_string("paste-failed").into_owned())
+                    .size(14)
+                    .color(Tone::Bad.color()),
+            );
+        }
+
+        let log_key = if self.show_log {
+            "hide-log"
+        } else {
+            "show-log"
+        };
+        column = column.push(
+            Button::new(
+                &mut self.log_button,
+                Text::new(self.resources.get_string(log_key).into_owned()).size(14),
+            )
+            .on_press(Message::ToggleLog),
+        );
+        if self.show_log {
+            column = self
+                .event_log
+                .iter()
+                .fold(column, |column, (time, status)| {
+                    let event = self.resources.get_string(event_log::label_key(status));
+                    column.push(
+                        Text::new(format!("{} {}", time, event))
+                            .size(14)
+                            .color(Tone::of(status).color()),
+                    )
+                });
         }
 
         Container::new(column)
@@ -138,22 +1256,116 @@ impl Application for App {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        subscription::run(
-            TypeId::of::<Status>(),
-            WatchStream::new(self.status_rx.clone()),
-        )
-        .map(Message::Status)
+        let discovery = if self.current_config.discover_port {
+            iced::time::every(DISCOVERY_INTERVAL).map(|_| Message::DiscoverPort)
+        } else {
+            Subscription::none()
+        };
+        let status = match &self.status_rx {
+            Some(status_rx) if self.current_config.low_power_ui => subscription::run(
+                (TypeId::of::<Status>(), LOW_POWER_INTERVAL),
+                throttled(status_rx.clone()),
+            ),
+            Some(status_rx) => {
+                subscription::run(TypeId::of::<Status>(), WatchStream::new(status_rx.clone()))
+            }
+            None => Subscription::none(),
+        };
+        let osc_in = match &self.osc_in_rx {
+            Some(osc_in_rx) => subscription::run(
+                TypeId::of::<osc::OscIn>(),
+                WatchStream::new(osc_in_rx.clone()),
+            ),
+            None => Subscription::none(),
+        };
+        Subscription::batch([
+            status.map(Message::Status),
+            osc_in.map(Message::OscInError),
+            subscription::events_with(window_event),
+            discovery,
+        ])
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.current_config
+            .ui_scale
+            .clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end())
+            .into()
+    }
+
+    fn should_exit(&self) -> bool {
+        self.should_exit
+    }
+}
+
+/// Passes on the latest status at most once per [`LOW_POWER_INTERVAL`], so
+/// that the window isn't redrawn for every reading.
+fn throttled(status_rx: watch::Receiver<Status>) -> impl Stream<Item = Status> {
+    futures::stream::unfold((status_rx, false), |(mut status_rx, wait)| async move {
+        if wait {
+            tokio::time::sleep(LOW_POWER_INTERVAL).await;
+            status_rx.changed().await.ok()?;
+        }
+        let status = status_rx.borrow_and_update().clone();
+        Some((status, (status_rx, true)))
+    })
+}
+
+/// The default window size for the current mode and UI scale.
+fn scaled_window_size(config: &Configuration) -> (u32, u32) {
+    let scale = config
+        .ui_scale
+        .clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+    let size = if config.mini_mode {
+        MINI_WINDOW_SIZE
+    } else {
+        WINDOW_SIZE
+    };
+    (
+        (size.0 as f32 * scale) as u32,
+        (size.1 as f32 * scale) as u32,
+    )
+}
+
+fn window_event(event: Event, status: event::Status) -> Option<Message> {
+    match event {
+        Event::Window(window::Event::Resized { width, height }) => {
+            Some(Message::WindowResized(width, height))
+        }
+        Event::Window(window::Event::CloseRequested) => Some(Message::CloseRequested),
+        // Keys typed into a text input aren't shortcuts.
+        Event::Keyboard(keyboard::Event::KeyPressed {
+            key_code,
+            modifiers,
+        }) if status == event::Status::Ignored && modifiers.control() => match key_code {
+            keyboard::KeyCode::K => Some(Message::UseObserved),
+            keyboard::KeyCode::Z => Some(Message::Undo),
+            keyboard::KeyCode::M => Some(Message::ToggleMiniMode),
+            keyboard::KeyCode::R => Some(Message::PanicReset),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
-fn load_font<I: IntoIterator<Item = V>, V: AsRef<str>>(names: I) -> Option<&'static [u8]> {
+/// Fonts which come with Windows and cover Chinese, Japanese, and Korean, for when the fonts
+/// named by the locale aren't installed. The built-in iced font can't render these scripts.
+const FALLBACK_FONTS: [&str; 5] = [
+    "Yu Gothic UI",
+    "Meiryo UI",
+    "Microsoft YaHei UI",
+    "Malgun Gothic",
+    "MS UI Gothic",
+];
+
+fn load_font<I: IntoIterator<Item = V>, V: AsRef<str>>(names: I) -> Option<Vec<u8>> {
     let font_source = SystemSource::new();
 
-    fn try_load_font(source: &SystemSource, name: &str) -> Option<&'static [u8]> {
+    fn try_load_font(source: &SystemSource, name: &str) -> Option<Vec<u8>> {
         let family = source.select_family_by_name(name).ok()?;
         let font = family.fonts().iter().next()?.load().ok()?;
         let data = font.copy_font_data()?;
-        Some((*data).clone().leak())
+        Some(Arc::try_unwrap(data).unwrap_or_else(|data| (*data).clone()))
     }
 
     names
@@ -162,6 +1374,25 @@ fn load_font<I: IntoIterator<Item = V>, V: AsRef<str>>(names: I) -> Option<&'sta
         .next()
 }
 
+fn font_families() -> Vec<String> {
+    let mut families = SystemSource::new().all_families().unwrap_or_default();
+    families.sort();
+    families.dedup();
+    families
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FontChoice {
+    family: Option<String>,
+    label: String,
+}
+
+impl fmt::Display for FontChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
 fn load_icon() -> Icon {
     let mut reader = ImageReader::new(Cursor::new(include_bytes!("../icon.png")));
     reader.set_format(ImageFormat::Png);
@@ -170,23 +1401,128 @@ fn load_icon() -> Icon {
     Icon::from_rgba(image.into_vec(), width, height).unwrap()
 }
 
+/// Prints the controllers the system can see, for troubleshooting.
+/// This is a GUI program, so output only shows up in a console if we ask for it.
+fn attach_console() {
+    unsafe {
+        Console::AttachConsole(Console::ATTACH_PARENT_PROCESS);
+    }
+}
+
+fn enumerate(json: bool) -> anyhow::Result<()> {
+    attach_console();
+
+    let devices = joycon::enumerate();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&devices)?);
+        return Ok(());
+    }
+
+    if devices.is_empty() {
+        println!("No controllers found");
+    }
+    for device in devices {
+        let battery = match device.battery {
+            Some(Battery {
+                percent,
+                charging: true,
+            }) => format!("{}% (charging)", percent),
+            Some(Battery { percent, .. }) => format!("{}%", percent),
+            None => "unknown".to_string(),
+        };
+        println!("{:?}\t{}\tbattery {}", device.kind, device.serial, battery);
+    }
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
-    if std::env::args().skip(1).collect::<Vec<_>>() == ["agent"] {
-        return agent::run();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["agent"] => return agent::run(),
+        ["enumerate"] => return enumerate(false),
+        ["enumerate", "--json"] => return enumerate(true),
+        ["service"] => return service::run(None),
+        ["service", path] => return service::run(Some(path.into())),
+        ["record", path] => {
+            attach_console();
+            return playback::record(Path::new(path));
+        }
+        ["replay", path] => {
+            attach_console();
+            return playback::replay(Path::new(path), false);
+        }
+        ["replay", path, "--dry-run"] => {
+            attach_console();
+            return playback::replay(Path::new(path), true);
+        }
+        #[cfg(feature = "sub-command-console")]
+        ["sub-command"] => {
+            attach_console();
+            return joycon::sub_command_console(std::io::stdin().lock())
+                .map_err(|err| anyhow::anyhow!("{:?}", err));
+        }
+        _ => {}
+    }
+    let status_json = args.iter().any(|arg| arg == "--status-json");
+    let skip_self_test = args.iter().any(|arg| arg == "--skip-self-test");
+    if status_json {
+        attach_console();
     }
 
     let resources = internationalization::Resources::new();
+    let first_run = !config::exists();
+    let config = config::load().unwrap_or_default();
+
+    let font = config
+        .font_family
+        .as_deref()
+        .and_then(|family| load_font([family]))
+        .or_else(|| load_font(resources.fonts()))
+        .or_else(|| {
+            eprintln!("None of the locale's fonts are installed, using a fallback");
+            load_font(FALLBACK_FONTS)
+        });
+
+    let problems = if skip_self_test {
+        Vec::new()
+    } else {
+        self_test::check(
+            Path::new("res"),
+            &resources.get_string("title"),
+            font.is_some(),
+        )
+    };
+    for problem in &problems {
+        eprintln!("Self test: {:?}", problem);
+    }
+
+    let size = match config.window_size {
+        Some(size) if !config.mini_mode => size,
+        _ => scaled_window_size(&config),
+    };
 
-    let font = load_font(resources.fonts());
+    // iced needs the default font for as long as the program runs, so this is the one place the
+    // font data is deliberately leaked.
+    let font: Option<&'static [u8]> = font.map(|data| &*Box::leak(data.into_boxed_slice()));
+    let mini_mode = config.mini_mode;
 
     App::run(Settings {
         default_font: font,
-        flags: Some(resources),
+        flags: Some(Flags {
+            resources,
+            config,
+            status_json,
+            problems,
+            first_run,
+        }),
         window: iced::window::Settings {
-            size: (384, 128),
+            size,
+            // Only read at startup, so switching modes takes effect after restarting.
+            always_on_top: mini_mode,
             icon: Some(load_icon()),
             ..Default::default()
         },
+        exit_on_close_request: false,
         ..Default::default()
     })
     .unwrap();
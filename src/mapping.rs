@@ -0,0 +1,766 @@
+use std::{
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    gesture::{ClenchDetector, ClutchDetector},
+    messages::{Configuration, StickSettings},
+    speed::{SpeedCategory, SpeedTracker},
+};
+
+const VRCHAT_FLOAT_RANGE: RangeInclusive<f32> = -1.0..=1.0;
+/// How close smoothing has to get to the target value to count as settled.
+const SETTLED: f32 = 0.001;
+/// How far past the configured input range readings may go before the
+/// calibration counts as wrong.
+const CALIBRATION_MARGIN: u8 = 3;
+
+/// A Ring-Con reading together with the value it maps to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MappedOutput {
+    /// The raw flex reading, 0 when no Ring-Con is attached.
+    pub flex: u8,
+    pub value: f32,
+    pub speed: SpeedCategory,
+    /// The readings seen don't fit the configured input range.
+    pub calibration_needed: bool,
+    /// On for a moment after a quick squeeze.
+    pub clench: bool,
+    /// On while the Ring-Con is held squeezed.
+    pub clutch: bool,
+    /// The stick from -1 to 1 on each axis, when `stick` is configured and
+    /// the report had it.
+    pub stick: Option<(f32, f32)>,
+}
+
+impl MappedOutput {
+    /// The output for when no Ring-Con is attached.
+    pub fn idle(config: &Configuration) -> Self {
+        Self {
+            flex: 0,
+            value: config.out_idle,
+            speed: SpeedCategory::Still,
+            calibration_needed: false,
+            clench: false,
+            clutch: false,
+            stick: None,
+        }
+    }
+
+    /// Whether this is the idle value sent while no Ring-Con is attached.
+    pub fn is_idle(&self) -> bool {
+        self.flex == 0
+    }
+}
+
+/// Evaluates the piecewise linear curve through `points`, which are sorted
+/// by input. Inputs past the first or last point take its output.
+pub fn apply_curve(points: &[(f32, f32)], input: f32) -> f32 {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return input,
+    };
+    if input <= first.0 {
+        return first.1;
+    }
+    if input >= last.0 {
+        return last.1;
+    }
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if input <= x1 {
+            if x1 == x0 {
+                return y1;
+            }
+            return y0 + (input - x0) / (x1 - x0) * (y1 - y0);
+        }
+    }
+    last.1
+}
+
+/// Maps raw stick readings to -1..1 on each axis, with the deadzone around
+/// the center reading 0.
+pub fn map_stick(raw: (u16, u16), settings: &StickSettings) -> (f32, f32) {
+    let axis = |raw: u16, center: u16, range: u16| {
+        let offset = i32::from(raw) - i32::from(center);
+        let deadzone = i32::from(settings.deadzone);
+        if offset.abs() <= deadzone {
+            return 0.0;
+        }
+        let span = (i32::from(range) - deadzone).max(1);
+        let past = offset - offset.signum() * deadzone;
+        (past as f32 / span as f32).clamp(-1.0, 1.0)
+    };
+    (
+        axis(raw.0, settings.center.0, settings.range.0),
+        axis(raw.1, settings.center.1, settings.range.1),
+    )
+}
+
+/// Maps flex to -1..1 around `in_center`, independent of `out_range`, for
+/// parameters where pulling and squeezing are two directions of one axis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bidirectional {
+    center: u8,
+    range: RangeInclusive<u8>,
+}
+
+impl Bidirectional {
+    pub fn new(config: &Configuration) -> Self {
+        Self {
+            center: config.in_center,
+            range: config.in_range.clone(),
+        }
+    }
+
+    /// Pulling is positive and squeezing negative. No Ring-Con counts as the
+    /// center.
+    pub fn value(&self, flex: u8) -> f32 {
+        let value = if flex == 0 || flex == self.center {
+            0.0
+        } else if flex < self.center {
+            let span = self.center.saturating_sub(*self.range.start()).max(1);
+            (self.center - flex) as f32 / span as f32
+        } else {
+            let span = self.range.end().saturating_sub(self.center).max(1);
+            -((flex - self.center) as f32) / span as f32
+        };
+        value.clamp(*VRCHAT_FLOAT_RANGE.start(), *VRCHAT_FLOAT_RANGE.end())
+    }
+}
+
+/// Whether the flex seen so far goes well past the configured input range,
+/// so that part of the Ring-Con's movement is lost to clamping.
+pub fn needs_calibration(observed: &RangeInclusive<u8>, in_range: &RangeInclusive<u8>) -> bool {
+    observed.start().saturating_add(CALIBRATION_MARGIN) < *in_range.start()
+        || *observed.end() > in_range.end().saturating_add(CALIBRATION_MARGIN)
+}
+
+/// Turns raw flex readings into output values according to a [`Configuration`].
+pub struct Mapper {
+    mid_in: u8,
+    mid_out: f32,
+    factor_low: f32,
+    factor_high: f32,
+    range_out: RangeInclusive<f32>,
+    /// `out_range` in its configured order, for placing values on the curve.
+    curve_range: (f32, f32),
+    curve: Vec<(f32, f32)>,
+    idle_out: f32,
+    hold: Option<Duration>,
+    /// Distance from `mid_out` at which the output is held.
+    hold_threshold: f32,
+    hold_decay: Duration,
+    held: Option<(f32, Instant)>,
+    gain: f32,
+    offset: f32,
+    extreme_mode: bool,
+    attack_tau: f32,
+    release_tau: f32,
+    smoothed: Option<(f32, Instant)>,
+    target: f32,
+    reset_on_insert: bool,
+    last_flex: Option<u8>,
+    speed: SpeedTracker,
+    /// The speed category of the last output.
+    mapped_speed: SpeedCategory,
+    clench: ClenchDetector,
+    clench_on: bool,
+    clutch: ClutchDetector,
+}
+
+impl Mapper {
+    pub fn new(config: &Configuration) -> Self {
+        let mut mapper = Self {
+            mid_in: 0,
+            mid_out: 0.0,
+            factor_low: 0.0,
+            factor_high: 0.0,
+            range_out: 0.0..=0.0,
+            curve_range: (0.0, 0.0),
+            curve: Vec::new(),
+            idle_out: 0.0,
+            hold: None,
+            hold_threshold: 0.0,
+            hold_decay: Duration::ZERO,
+            held: None,
+            gain: 1.0,
+            offset: 0.0,
+            extreme_mode: false,
+            attack_tau: 0.0,
+            release_tau: 0.0,
+            smoothed: None,
+            target: 0.0,
+            reset_on_insert: false,
+            last_flex: None,
+            speed: SpeedTracker::new(config.speed_thresholds),
+            mapped_speed: SpeedCategory::Still,
+            clench: ClenchDetector::new(config.clench),
+            clench_on: false,
+            clutch: ClutchDetector::new(config),
+        };
+        mapper.configure(config);
+        mapper
+    }
+
+    pub fn configure(&mut self, config: &Configuration) {
+        self.mid_in = config.in_center;
+        let half_out = (config.out_range.end() - config.out_range.start()) / 2.0;
+        self.mid_out = config.out_range.start() + half_out;
+        // At least one step, so that a center at the edge of the input range
+        // sends the rest of that side straight to the end of the output.
+        let span_low = config
+            .in_range
+            .end()
+            .saturating_sub(config.in_center)
+            .max(1);
+        let span_high = config
+            .in_center
+            .saturating_sub(*config.in_range.start())
+            .max(1);
+        self.factor_low = half_out / span_low as f32;
+        self.factor_high = -half_out / span_high as f32;
+        self.range_out = f32::min(*config.out_range.start(), *config.out_range.end())
+            ..=f32::max(*config.out_range.start(), *config.out_range.end());
+
+        self.curve_range = (*config.out_range.start(), *config.out_range.end());
+        self.curve = config
+            .curve_points
+            .iter()
+            .map(|&(x, y)| (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+            .collect();
+        self.curve.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        self.idle_out = config.out_idle;
+
+        self.hold = config.hold_ms.map(|ms| Duration::from_millis(ms.into()));
+        self.hold_threshold = config.hold_threshold * half_out.abs();
+        self.hold_decay = Duration::from_millis(config.hold_decay_ms.into());
+        self.held = None;
+
+        self.gain = config.gain;
+        self.offset = config.offset;
+        self.extreme_mode = config.extreme_mode;
+        self.attack_tau = config.attack_tau;
+        self.release_tau = config.release_tau;
+        self.smoothed = None;
+        self.reset_on_insert = config.reset_on_insert;
+        self.speed.set_thresholds(config.speed_thresholds);
+        self.clench.set_settings(config.clench);
+        self.clutch.configure(config);
+    }
+
+    /// Maps a reading and passes the result to `output`. When the Ring-Con
+    /// has just been inserted and resetting is configured, the idle value is
+    /// passed first.
+    pub fn map(&mut self, flex: u8, now: Instant, mut output: impl FnMut(MappedOutput)) {
+        let inserted = flex != 0 && self.last_flex.is_none_or(|prev| prev == 0);
+        self.last_flex = Some(flex);
+
+        let speed = self.speed.update(flex, now);
+        self.mapped_speed = speed;
+        let clench = self.clench.update(flex, now);
+        self.clench_on = clench;
+        let clutch = self.clutch.update(flex, now);
+
+        if inserted && self.reset_on_insert {
+            output(MappedOutput {
+                flex: 0,
+                value: self.value(0, now),
+                speed: SpeedCategory::Still,
+                calibration_needed: false,
+                clench: false,
+                clutch: false,
+                stick: None,
+            });
+        }
+        output(MappedOutput {
+            flex,
+            value: self.value(flex, now),
+            speed,
+            calibration_needed: false,
+            clench,
+            clutch,
+            stick: None,
+        });
+    }
+
+    /// Follows the speed with a reading which may not be mapped, since
+    /// unchanged readings are how motion stops. Returns whether the speed
+    /// category differs from the last output's, so it should be mapped.
+    pub fn track_speed(&mut self, flex: u8, now: Instant) -> bool {
+        self.speed.update(flex, now) != self.mapped_speed
+    }
+
+    fn value(&mut self, flex: u8, now: Instant) -> f32 {
+        // Both halves meet at `mid_out` when the flex is at the center, so
+        // the center needs no case of its own and goes through the curve,
+        // hold, gain and smoothing like any other reading.
+        let mut value = if flex == 0 {
+            self.idle_out
+        } else {
+            let factor = if flex < self.mid_in {
+                self.factor_low
+            } else {
+                self.factor_high
+            };
+            (self.mid_out + flex.abs_diff(self.mid_in) as f32 * factor)
+                .clamp(*self.range_out.start(), *self.range_out.end())
+        };
+
+        if flex == 0 {
+            self.held = None;
+            self.smoothed = None;
+        } else {
+            value = self.apply_curve(value);
+            if let Some(hold) = self.hold {
+                value = self.apply_hold(value, hold, now);
+            }
+            value = value * self.gain + self.offset;
+            if !self.extreme_mode {
+                value = value.clamp(*VRCHAT_FLOAT_RANGE.start(), *VRCHAT_FLOAT_RANGE.end());
+            }
+            value = self.apply_smoothing(value, now);
+        }
+
+        value
+    }
+
+    /// Whether smoothing is still moving the output towards the latest
+    /// reading, so it should be mapped again even if the reading is unchanged.
+    pub fn is_settling(&self) -> bool {
+        self.smoothed
+            .is_some_and(|(smoothed, _)| (smoothed - self.target).abs() > SETTLED)
+    }
+
+    /// Whether a clench pulse is on or a clutch is about to latch, so
+    /// readings should keep being mapped until that has happened.
+    pub fn is_pulsing(&self) -> bool {
+        self.clench_on || self.clutch.is_waiting()
+    }
+
+    /// Moves the previous output towards `value`, using the attack time
+    /// constant while rising and the release one while falling.
+    fn apply_smoothing(&mut self, value: f32, now: Instant) -> f32 {
+        self.target = value;
+        let smoothed = match self.smoothed {
+            Some((previous, since)) => {
+                let tau = if value > previous {
+                    self.attack_tau
+                } else {
+                    self.release_tau
+                };
+                if tau > 0.0 {
+                    let elapsed = now.duration_since(since).as_secs_f32();
+                    previous + (value - previous) * (1.0 - (-elapsed / tau).exp())
+                } else {
+                    value
+                }
+            }
+            None => value,
+        };
+        self.smoothed = Some((smoothed, now));
+        smoothed
+    }
+
+    /// Reshapes `value`, which is within `out_range`, along the configured
+    /// curve.
+    fn apply_curve(&self, value: f32) -> f32 {
+        let (start, end) = self.curve_range;
+        if self.curve.is_empty() || start == end {
+            return value;
+        }
+        let input = (value - start) / (end - start);
+        start + apply_curve(&self.curve, input) * (end - start)
+    }
+
+    /// Latches the value furthest from the center, once it is past the
+    /// threshold, until `hold` has passed, then moves back to the live value
+    /// over the decay time.
+    fn apply_hold(&mut self, value: f32, hold: Duration, now: Instant) -> f32 {
+        let distance = |value: f32| (value - self.mid_out).abs();
+        if let Some((held, since)) = self.held {
+            if distance(value) < distance(held) {
+                let elapsed = now.duration_since(since);
+                if elapsed < hold {
+                    return held;
+                }
+                let decayed = (elapsed - hold).as_secs_f32();
+                let decay = self.hold_decay.as_secs_f32();
+                if decayed < decay {
+                    return held + (value - held) * (decayed / decay);
+                }
+            }
+            self.held = None;
+        }
+        if distance(value) >= self.hold_threshold {
+            self.held = Some((value, now));
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{apply_curve, map_stick, needs_calibration, Bidirectional, Mapper};
+    use crate::messages::{Configuration, StickSettings};
+    use crate::speed::SpeedCategory;
+
+    fn config() -> Configuration {
+        Configuration {
+            discover_port: false,
+            idle_poll_ms: None,
+            reinit_attempts: 0,
+            ..Configuration::default()
+        }
+    }
+
+    fn map_all(mapper: &mut Mapper, flexes: &[u8], now: Instant) -> Vec<f32> {
+        let mut values = Vec::new();
+        for &flex in flexes {
+            mapper.map(flex, now, |output| values.push(output.value));
+        }
+        values
+    }
+
+    #[test]
+    fn maps_flex() {
+        let mut mapper = Mapper::new(&config());
+
+        let cases = [
+            (0, 0.0),
+            (15, 0.75),
+            (7, 0.75 + 8.0 * (0.25 / 9.0)),
+            (1, 1.0),
+            (20, 0.75 + 5.0 * (-0.25 / 8.0)),
+            (24, 0.5),
+            (255, 0.5),
+        ];
+        let now = Instant::now();
+        for (flex, expected) in cases {
+            assert_eq!(
+                map_all(&mut mapper, &[flex], now),
+                [expected],
+                "flex {}",
+                flex
+            );
+        }
+    }
+
+    #[test]
+    fn zero_width_out_range() {
+        let mut mapper = Mapper::new(&Configuration {
+            out_range: 0.5..=0.5,
+            ..config()
+        });
+
+        let values = map_all(&mut mapper, &[0, 1, 15, 24, 255], Instant::now());
+        assert_eq!(values, [0.0, 0.5, 0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn negative_out_range() {
+        let mut mapper = Mapper::new(&Configuration {
+            out_range: -1.0..=1.0,
+            ..config()
+        });
+
+        let values = map_all(&mut mapper, &[0, 15, 7, 1, 20, 24, 255], Instant::now());
+        assert_eq!(
+            values,
+            [0.0, 0.0, 0.0 + 8.0 * (1.0 / 9.0), 1.0, -0.625, -1.0, -1.0]
+        );
+    }
+
+    #[test]
+    fn reversed_out_range() {
+        let mut mapper = Mapper::new(&Configuration {
+            out_range: 1.0..=-1.0,
+            ..config()
+        });
+
+        let values = map_all(&mut mapper, &[15, 1, 20, 255], Instant::now());
+        assert_eq!(values, [0.0, -1.0, 0.625, 1.0]);
+    }
+
+    #[test]
+    fn out_range_past_one() {
+        let wide = Configuration {
+            out_range: 0.0..=2.0,
+            ..config()
+        };
+
+        let mut mapper = Mapper::new(&wide);
+        let values = map_all(&mut mapper, &[15, 1, 255], Instant::now());
+        assert_eq!(values, [1.0, 1.0, 0.0]);
+
+        let mut mapper = Mapper::new(&Configuration {
+            extreme_mode: true,
+            ..wide
+        });
+        let values = map_all(&mut mapper, &[15, 1, 255], Instant::now());
+        assert_eq!(values, [1.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn gain_and_offset() {
+        let mut mapper = Mapper::new(&Configuration {
+            gain: 4.0,
+            offset: -2.0,
+            ..config()
+        });
+
+        let values = map_all(&mut mapper, &[0, 24, 15, 1], Instant::now());
+        assert_eq!(values, [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn extreme_mode_is_unclamped() {
+        let mut mapper = Mapper::new(&Configuration {
+            gain: 4.0,
+            offset: -2.0,
+            extreme_mode: true,
+            ..config()
+        });
+
+        let values = map_all(&mut mapper, &[0, 24, 1], Instant::now());
+        assert_eq!(values, [0.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn insert_without_reset() {
+        let mut mapper = Mapper::new(&config());
+
+        let values = map_all(&mut mapper, &[15, 20], Instant::now());
+        assert_eq!(values, [0.75, 0.75 + 5.0 * (-0.25 / 8.0)]);
+    }
+
+    #[test]
+    fn insert_with_reset() {
+        let mut mapper = Mapper::new(&Configuration {
+            reset_on_insert: true,
+            ..config()
+        });
+
+        let values = map_all(&mut mapper, &[15, 15, 0, 15], Instant::now());
+        assert_eq!(values, [0.0, 0.75, 0.75, 0.0, 0.0, 0.75]);
+    }
+
+    #[test]
+    fn hold_latches_extreme() {
+        let mut mapper = Mapper::new(&Configuration {
+            hold_ms: Some(200),
+            ..config()
+        });
+
+        let start = Instant::now();
+        assert_eq!(map_all(&mut mapper, &[24], start), [0.5]);
+        let later = start + Duration::from_millis(100);
+        assert_eq!(map_all(&mut mapper, &[15], later), [0.5]);
+        let expired = start + Duration::from_millis(200);
+        assert_eq!(map_all(&mut mapper, &[15], expired), [0.5]);
+        let decaying = start + Duration::from_millis(250);
+        assert_eq!(map_all(&mut mapper, &[15], decaying), [0.625]);
+        let decayed = start + Duration::from_millis(300);
+        assert_eq!(map_all(&mut mapper, &[15], decayed), [0.75]);
+    }
+
+    #[test]
+    fn hold_needs_threshold() {
+        let mut mapper = Mapper::new(&Configuration {
+            hold_ms: Some(200),
+            hold_decay_ms: 0,
+            ..config()
+        });
+
+        // 0.6875 is only a quarter of the way from 0.75 to 0.5, short of the
+        // default threshold of half way.
+        let start = Instant::now();
+        assert_eq!(map_all(&mut mapper, &[17], start), [0.6875]);
+        let later = start + Duration::from_millis(100);
+        assert_eq!(map_all(&mut mapper, &[15], later), [0.75]);
+
+        assert_eq!(map_all(&mut mapper, &[21], later), [0.5625]);
+        let held = later + Duration::from_millis(100);
+        assert_eq!(map_all(&mut mapper, &[16], held), [0.5625]);
+    }
+
+    #[test]
+    fn hold_released_by_idle() {
+        let mut mapper = Mapper::new(&Configuration {
+            hold_ms: Some(200),
+            ..config()
+        });
+
+        let values = map_all(&mut mapper, &[24, 0, 15], Instant::now());
+        assert_eq!(values, [0.5, 0.0, 0.75]);
+    }
+
+    #[test]
+    fn attack_and_release() {
+        let mut mapper = Mapper::new(&Configuration {
+            attack_tau: 1.0,
+            release_tau: 0.0,
+            ..config()
+        });
+
+        let start = Instant::now();
+        assert_eq!(map_all(&mut mapper, &[24], start), [0.5]);
+        // Rising from 0.5 to 1.0 covers 1 - 1/e of the way in one time constant.
+        let later = start + Duration::from_secs(1);
+        let risen = map_all(&mut mapper, &[1], later)[0];
+        assert!(
+            (risen - (1.0 - 0.5 / std::f32::consts::E)).abs() < 1e-4,
+            "{}",
+            risen
+        );
+        assert!(mapper.is_settling());
+        // Falling is immediate.
+        assert_eq!(map_all(&mut mapper, &[24], later), [0.5]);
+        assert!(!mapper.is_settling());
+    }
+
+    #[test]
+    fn speed_drops_on_unchanged_readings() {
+        let mut mapper = Mapper::new(&config());
+        let start = Instant::now();
+        let mut speed = None;
+        let mut map = |mapper: &mut Mapper, ms: u64| {
+            mapper.map(20, start + Duration::from_millis(ms), |output| {
+                speed = Some(output.speed)
+            });
+            speed.unwrap()
+        };
+        mapper.map(10, start, |_| {});
+        assert_eq!(map(&mut mapper, 250), SpeedCategory::Fast);
+
+        // Only readings whose speed changed are mapped, as when unchanged
+        // readings are otherwise held back.
+        let mut changes = Vec::new();
+        for ms in (266..1000).step_by(16) {
+            if mapper.track_speed(20, start + Duration::from_millis(ms)) {
+                changes.push(map(&mut mapper, ms));
+            }
+        }
+        assert_eq!(changes, [SpeedCategory::Slow, SpeedCategory::Still]);
+    }
+
+    #[test]
+    fn smoothing_skips_idle_transition() {
+        let mut mapper = Mapper::new(&Configuration {
+            attack_tau: 1.0,
+            release_tau: 1.0,
+            ..config()
+        });
+
+        let start = Instant::now();
+        assert_eq!(map_all(&mut mapper, &[24], start), [0.5]);
+        // Letting go resets straight to the idle value.
+        let later = start + Duration::from_millis(100);
+        assert_eq!(map_all(&mut mapper, &[0], later), [0.0]);
+        // The next reading starts over instead of rising from the idle value.
+        let again = later + Duration::from_millis(100);
+        assert_eq!(map_all(&mut mapper, &[15], again), [0.75]);
+        assert!(!mapper.is_settling());
+    }
+
+    #[test]
+    fn calibration_margin() {
+        let in_range = 7..=24;
+        assert!(!needs_calibration(&(7..=24), &in_range));
+        assert!(!needs_calibration(&(10..=20), &in_range));
+        assert!(!needs_calibration(&(4..=27), &in_range));
+        assert!(needs_calibration(&(3..=24), &in_range));
+        assert!(needs_calibration(&(7..=28), &in_range));
+    }
+
+    #[test]
+    fn bidirectional_around_center() {
+        let bidirectional = Bidirectional::new(&config());
+
+        let cases = [
+            (0, 0.0),
+            (15, 0.0),
+            (11, 0.5),
+            (7, 1.0),
+            (1, 1.0),
+            (24, -1.0),
+            (255, -1.0),
+        ];
+        for (flex, expected) in cases {
+            assert_eq!(bidirectional.value(flex), expected, "flex {}", flex);
+        }
+    }
+
+    #[test]
+    fn curve_between_points() {
+        let points = [(0.0, 0.0), (0.5, 0.25), (1.0, 1.0)];
+        assert_eq!(apply_curve(&points, 0.0), 0.0);
+        assert_eq!(apply_curve(&points, 0.25), 0.125);
+        assert_eq!(apply_curve(&points, 0.5), 0.25);
+        assert_eq!(apply_curve(&points, 0.75), 0.625);
+        assert_eq!(apply_curve(&points, 1.0), 1.0);
+        assert_eq!(apply_curve(&[], 0.3), 0.3);
+        assert_eq!(apply_curve(&[(0.2, 0.4), (0.8, 0.6)], 0.1), 0.4);
+    }
+
+    #[test]
+    fn curve_follows_out_range() {
+        // Flat at the center of the range, whichever way round it is.
+        for out_range in [0.5..=1.0, 1.0..=0.5] {
+            let mut mapper = Mapper::new(&Configuration {
+                out_range,
+                curve_points: vec![(1.0, 0.5), (0.0, 0.5)],
+                ..config()
+            });
+            let values = map_all(&mut mapper, &[0, 1, 15, 255], Instant::now());
+            assert_eq!(values, [0.0, 0.75, 0.75, 0.75]);
+        }
+    }
+
+    #[test]
+    fn center_is_continuous() {
+        let mut mapper = Mapper::new(&config());
+        let values = map_all(&mut mapper, &[14, 15, 16], Instant::now());
+        assert_eq!(values[1], 0.75);
+        assert!(values[0] > values[1] && values[1] > values[2]);
+        assert!((values[0] - values[1]) < 0.05 && (values[1] - values[2]) < 0.05);
+    }
+
+    #[test]
+    fn center_goes_through_curve() {
+        let mut mapper = Mapper::new(&Configuration {
+            curve_points: vec![(0.0, 0.0), (0.5, 0.8), (1.0, 1.0)],
+            ..config()
+        });
+        let values = map_all(&mut mapper, &[15], Instant::now());
+        assert_eq!(values, [0.5 + 0.8 * 0.5]);
+    }
+
+    #[test]
+    fn center_at_edge_of_input_range() {
+        let mut mapper = Mapper::new(&Configuration {
+            in_center: 24,
+            ..config()
+        });
+        let values = map_all(&mut mapper, &[7, 24, 255], Instant::now());
+        assert_eq!(values, [1.0, 0.75, 0.5]);
+    }
+
+    #[test]
+    fn stick_deadzone_and_range() {
+        let settings = StickSettings {
+            center: (2000, 2100),
+            range: (1100, 1100),
+            deadzone: 100,
+            ..StickSettings::default()
+        };
+        assert_eq!(map_stick((2000, 2100), &settings), (0.0, 0.0));
+        assert_eq!(map_stick((2100, 2000), &settings), (0.0, 0.0));
+        assert_eq!(map_stick((2600, 1500), &settings), (0.5, -0.5));
+        assert_eq!(map_stick((4095, 0), &settings), (1.0, -1.0));
+    }
+}
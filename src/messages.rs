@@ -1,19 +1,75 @@
-use std::{net::SocketAddr, ops::RangeInclusive};
+use std::{
+    collections::BTreeMap,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    ops::RangeInclusive,
+};
 
 use serde::{Deserialize, Serialize};
 
+use crate::speed::SpeedCategory;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub(crate) enum Status {
+pub enum Status {
     NotConnected,
     Initializing(InitializationStep),
     NoRingCon,
-    Active(u8),
+    Active(Reading),
     Disconnected,
+    /// A controller connected which can't read the Ring-Con. Still waiting for
+    /// a right Joy-Con.
+    UnsupportedDevice(DeviceKind),
+    /// Several right Joy-Cons are connected. Waiting for `preferred_serial`
+    /// to be set to one of them.
+    MultipleDevices(Vec<DeviceInfo>),
+    /// The Joy-Con couldn't be opened or set up, with the system's error.
+    /// Usually another program is using it. Tried again every second.
+    HardwareError(String),
+    /// The Joy-Con never started the MCU which talks to the Ring-Con, so it
+    /// is probably newer or unofficial hardware. Waiting for another
+    /// controller.
+    UnsupportedFirmware,
+}
+
+impl Status {
+    /// Number sent to `status_address`: 0 for not connected, 1 for
+    /// initializing, 2 for no Ring-Con, 3 for active, 4 for disconnected, 5
+    /// for an unsupported controller, 6 while waiting for a choice between
+    /// several Joy-Cons, 7 when the Joy-Con couldn't be opened and 8 when its
+    /// MCU didn't start.
+    pub fn code(&self) -> i32 {
+        match self {
+            Status::NotConnected => 0,
+            Status::Initializing(_) => 1,
+            Status::NoRingCon => 2,
+            Status::Active(_) => 3,
+            Status::Disconnected => 4,
+            Status::UnsupportedDevice(_) => 5,
+            Status::MultipleDevices(_) => 6,
+            Status::HardwareError(_) => 7,
+            Status::UnsupportedFirmware => 8,
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
-pub(crate) enum InitializationStep {
-    Configuring = 0,
+/// Everything about the latest reading, so the UI doesn't have to redo the
+/// mapping.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reading {
+    /// The raw flex reading.
+    pub flex: u8,
+    /// The range of flex seen so far.
+    pub observed: RangeInclusive<u8>,
+    /// The value sent to `osc_address`, after hold, gain and smoothing.
+    pub value: f32,
+    pub speed: SpeedCategory,
+    pub battery: Battery,
+    /// The readings go well past the configured input range.
+    pub calibration_needed: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InitializationStep {
+    Configuring,
     McuState,
     McuConfiguration0,
     McuConfiguration1,
@@ -23,12 +79,611 @@ pub(crate) enum InitializationStep {
     Step7,
 }
 
+impl InitializationStep {
+    /// Every step, in the order they happen.
+    pub const ALL: [InitializationStep; 8] = [
+        InitializationStep::Configuring,
+        InitializationStep::McuState,
+        InitializationStep::McuConfiguration0,
+        InitializationStep::McuConfiguration1,
+        InitializationStep::Step4,
+        InitializationStep::Step5,
+        InitializationStep::Step6,
+        InitializationStep::Step7,
+    ];
+
+    /// Fraction of the initialization completed when this step starts.
+    pub fn progress(self) -> f32 {
+        let index = Self::ALL.iter().position(|&s| s == self).unwrap();
+        index as f32 / Self::ALL.len() as f32
+    }
+
+    /// Fluent message describing this step.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            InitializationStep::Configuring => "init-configuring",
+            InitializationStep::McuState => "init-mcu-state",
+            InitializationStep::McuConfiguration0 | InitializationStep::McuConfiguration1 => {
+                "init-mcu-configuration"
+            }
+            InitializationStep::Step4 => "init-detecting",
+            InitializationStep::Step5 => "init-configuring-ringcon",
+            InitializationStep::Step6 => "init-polling",
+            InitializationStep::Step7 => "init-finishing",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceKind {
+    JoyConL,
+    JoyConR,
+    ProCon,
+}
+
+impl DeviceKind {
+    /// Fluent message explaining why this controller isn't used.
+    pub fn unsupported_key(self) -> &'static str {
+        match self {
+            DeviceKind::JoyConL => "unsupported-joycon-l",
+            DeviceKind::ProCon => "unsupported-procon",
+            DeviceKind::JoyConR => "unsupported-device",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Battery {
+    pub percent: u8,
+    pub charging: bool,
+}
+
+impl Battery {
+    /// Decodes the battery nibble from byte 2 of a standard input report.
+    pub fn from_report_byte(byte: u8) -> Self {
+        let nibble = byte >> 4;
+        let level = u16::from((nibble & 0xe).min(8));
+        Battery {
+            percent: (level * 100 / 8) as u8,
+            charging: nibble & 1 != 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub kind: DeviceKind,
+    pub serial: String,
+    pub battery: Option<Battery>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub(crate) struct Configuration {
+pub struct Configuration {
+    /// Which [`CONFIG_VERSION`] the settings were written for. Files from
+    /// before there were versions don't have it, and count as 0.
+    #[serde(default)]
+    pub version: u32,
+    /// Where OSC messages are sent. This may be a multicast group, to reach
+    /// several listeners at once.
     pub udp_address: SocketAddr,
+    /// How many routers multicast messages may cross.
+    #[serde(default = "default_multicast_ttl")]
+    pub multicast_ttl: u32,
+    /// Switch `udp_address` to the port VRChat is listening on if nothing is
+    /// listening on the configured one.
+    #[serde(default = "default_discover_port")]
+    pub discover_port: bool,
+    /// Where the agent listens for what VRChat sends over OSC, such as
+    /// avatar changes. VRChat sends to 9001 unless told otherwise.
+    #[serde(default = "default_osc_in_port")]
+    pub osc_in_port: u16,
+    /// Serial number of the right Joy-Con to use when several are connected.
+    pub preferred_serial: Option<String>,
+    /// Start reading the Joy-Con as soon as the program opens, instead of
+    /// waiting for "Start" to be pressed.
+    #[serde(default = "default_auto_start")]
+    pub auto_start: bool,
     pub osc_address: String,
+    /// Send the smoothing sliders while they are dragged, and apply the
+    /// address once its field is left. Otherwise the sliders apply when let
+    /// go and the address when asked to.
+    #[serde(default)]
+    pub live_apply: bool,
     pub in_range: RangeInclusive<u8>,
     pub in_center: u8,
+    /// Where `in_range` maps to. If both ends are the same, every reading maps
+    /// to that value. The ends may be negative, larger than 1 or in either
+    /// order, but values outside -1 to 1 are only sent with `extreme_mode`.
     pub out_range: RangeInclusive<f32>,
     pub out_idle: f32,
+    /// When `out_idle` is sent instead of a reading.
+    #[serde(default)]
+    pub idle_on: IdleTrigger,
+    /// Reshapes the output with straight lines between `(input, output)`
+    /// points, both from 0 at the start of `out_range` to 1 at its end. Empty
+    /// leaves the output as it is.
+    #[serde(default)]
+    pub curve_points: Vec<(f32, f32)>,
+    /// Keep emitting the most extreme output for this long after it is reached.
+    pub hold_ms: Option<u32>,
+    /// How far the output has to get from the middle of `out_range` to be
+    /// held, as a fraction of the way to its end.
+    #[serde(default = "default_hold_threshold")]
+    pub hold_threshold: f32,
+    /// Once the hold is over, move from the held output to the live one over
+    /// this many milliseconds.
+    #[serde(default = "default_hold_decay_ms")]
+    pub hold_decay_ms: u32,
+    /// Applied to the mapped output as `value * gain + offset`.
+    #[serde(default = "default_gain")]
+    pub gain: f32,
+    #[serde(default)]
+    pub offset: f32,
+    /// Send values outside of VRChat's -1 to 1 float parameter range.
+    #[serde(default)]
+    pub extreme_mode: bool,
+    /// Send the idle value before the first reading after the Ring-Con is inserted.
+    #[serde(default)]
+    pub reset_on_insert: bool,
+    /// Time constants in seconds for smoothing the output while it rises and
+    /// falls. 0 disables smoothing.
+    #[serde(default)]
+    pub attack_tau: f32,
+    #[serde(default)]
+    pub release_tau: f32,
+    /// Send the output to the first address instead of `osc_address`, and its complement
+    /// (`1.0 - value`) to the second, for pairs of opposing blend shapes.
+    pub complementary: Option<(String, String)>,
+    /// Poll the Joy-Con this often once the Ring-Con has been missing for a
+    /// while, to save power.
+    pub idle_poll_ms: Option<u32>,
+    /// Stop sending and poll like `idle_poll_ms` once the flex has stayed
+    /// put for this many seconds, until it moves again.
+    pub auto_pause_s: Option<u32>,
+    /// How many times to set up the Ring-Con again while it reads as missing,
+    /// in case it lost its setup when it was taken out.
+    #[serde(default = "default_reinit_attempts")]
+    pub reinit_attempts: u32,
+    /// How many reports to ignore after the Ring-Con is set up, while its
+    /// first readings can still jump around.
+    #[serde(default = "default_warmup_reports")]
+    pub warmup_reports: u32,
+    /// How long the Ring-Con may read as missing after setup before that is
+    /// reported, since it can take a moment to show up even when attached.
+    #[serde(default = "default_ringcon_grace_ms")]
+    pub ringcon_grace_ms: u32,
+    /// Show on the player LED whether the Ring-Con is being read: solid while
+    /// it is, flashing while waiting for it and off if it couldn't be set up.
+    #[serde(default)]
+    pub led_feedback: bool,
+    /// When to send readings over OSC.
+    #[serde(default)]
+    pub resend: ResendPolicy,
+    /// Round the output to this many steps between -1 and 1 before sending,
+    /// and skip readings which round to what was last sent. VRChat syncs
+    /// float parameters with 256 steps.
+    pub quantize_steps: Option<u32>,
+    /// Never send readings closer together than this, in milliseconds, so
+    /// that VRChat doesn't start ignoring them. The latest reading is sent
+    /// once the time is up. 0 sends every reading right away.
+    #[serde(default = "default_min_packet_interval_ms")]
+    pub min_packet_interval_ms: u32,
+    /// Keep sending the idle value this often while no Joy-Con is connected,
+    /// so that VRChat gets it even if it was started afterwards.
+    pub idle_resend_ms: Option<u32>,
+    /// Also send how fast the Ring-Con is flexed to this address, as an int:
+    /// 0 for still, 1 for slow and 2 for fast.
+    pub speed_category_address: Option<String>,
+    /// Also send the flex to this address as a float from -1 for squeezed to
+    /// 1 for pulled, with `in_center` at 0. `out_range`, hold, gain and
+    /// smoothing don't apply.
+    pub bidirectional_address: Option<String>,
+    /// Also send whether the readings go well past `in_range`, as a bool, so
+    /// the avatar can show that the Ring-Con needs calibrating.
+    pub calibration_needed_address: Option<String>,
+    /// Also send a bool to this address, which turns on briefly when the
+    /// Ring-Con is squeezed quickly.
+    pub clench_address: Option<String>,
+    /// Also send a bool to this address, which turns on once the Ring-Con
+    /// has been squeezed to `clutch_on` or further for `clutch_ms`, and off
+    /// again once it is let go below `clutch_off`.
+    pub clutch_address: Option<String>,
+    #[serde(default = "default_clutch_ms")]
+    pub clutch_ms: u32,
+    #[serde(default = "default_clutch_on")]
+    pub clutch_on: u8,
+    #[serde(default = "default_clutch_off")]
+    pub clutch_off: u8,
+    /// Also send what the program is doing to this address, as an int, so
+    /// the avatar can show that it is still connecting. See [`Status::code`].
+    pub status_address: Option<String>,
+    #[serde(default)]
+    pub clench: ClenchSettings,
+    /// Also send the Joy-Con's stick, from -1 to 1 on each axis.
+    pub stick: Option<StickSettings>,
+    /// Flex steps per second at which the speed category becomes slow and fast.
+    #[serde(default = "default_speed_thresholds")]
+    pub speed_thresholds: (f32, f32),
+    /// Font family for the UI, instead of the one chosen by the locale.
+    pub font_family: Option<String>,
+    /// Multiplier for the size of everything in the UI.
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+    /// How ranges are shown in the UI. Doesn't affect what is stored or sent.
+    #[serde(default)]
+    pub display_unit: DisplayUnit,
+    /// Show only a single line of status in a small window which stays on top.
+    #[serde(default)]
+    pub mini_mode: bool,
+    /// Redraw the window a few times a second instead of for every reading,
+    /// to save power.
+    #[serde(default)]
+    pub low_power_ui: bool,
+    /// Play a sound when the Ring-Con connects or disconnects, and when the
+    /// input range is replaced, for when the window can't be seen.
+    #[serde(default)]
+    pub audio_cues: bool,
+    /// Serve Prometheus metrics on `http://127.0.0.1:{port}/metrics` from the
+    /// agent. Takes effect when the agent is started.
+    pub metrics_port: Option<u16>,
+    /// Sign every packet with this secret, for a forwarder which checks
+    /// where packets come from. VRChat can't read signed packets.
+    pub shared_secret: Option<String>,
+    /// Print every OSC packet sent, decoded, for checking what goes where.
+    #[serde(default)]
+    pub log_packets: bool,
+    /// Keep reading the Joy-Con for this many seconds after the window is
+    /// gone, so that a new window can take over without setting it up again.
+    pub agent_linger_s: Option<u32>,
+    /// What the agent does with readings while it lingers without a window.
+    #[serde(default)]
+    pub orphaned: OrphanPolicy,
+    /// Logical size of the full window when it was last closed.
+    pub window_size: Option<(u32, u32)>,
+    /// Settings to use while wearing an avatar, by the avatar id VRChat sends
+    /// to `/avatar/change`. The agent only listens for it while there are any.
+    #[serde(default)]
+    pub avatar_profiles: BTreeMap<String, AvatarProfile>,
+}
+
+/// Settings for when nothing has been saved yet, which suit VRChat running
+/// on the same computer.
+impl Default for Configuration {
+    fn default() -> Self {
+        Configuration {
+            version: CONFIG_VERSION,
+            udp_address: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 9000)),
+            multicast_ttl: default_multicast_ttl(),
+            discover_port: default_discover_port(),
+            osc_in_port: default_osc_in_port(),
+            preferred_serial: None,
+            auto_start: default_auto_start(),
+            osc_address: "/avatar/parameters/ringcon_flex".to_string(),
+            live_apply: false,
+            in_center: 15,
+            in_range: 7..=24,
+            out_idle: 0.0,
+            idle_on: IdleTrigger::default(),
+            out_range: 0.5..=1.0,
+            curve_points: Vec::new(),
+            hold_ms: None,
+            hold_threshold: default_hold_threshold(),
+            hold_decay_ms: default_hold_decay_ms(),
+            gain: default_gain(),
+            offset: 0.0,
+            extreme_mode: false,
+            reset_on_insert: false,
+            attack_tau: 0.0,
+            release_tau: 0.0,
+            complementary: None,
+            idle_poll_ms: Some(250),
+            auto_pause_s: None,
+            resend: ResendPolicy::default(),
+            quantize_steps: None,
+            min_packet_interval_ms: default_min_packet_interval_ms(),
+            idle_resend_ms: None,
+            reinit_attempts: default_reinit_attempts(),
+            warmup_reports: default_warmup_reports(),
+            ringcon_grace_ms: default_ringcon_grace_ms(),
+            led_feedback: false,
+            speed_category_address: None,
+            bidirectional_address: None,
+            calibration_needed_address: None,
+            clench_address: None,
+            clutch_address: None,
+            clutch_ms: default_clutch_ms(),
+            clutch_on: default_clutch_on(),
+            clutch_off: default_clutch_off(),
+            status_address: None,
+            clench: ClenchSettings::default(),
+            stick: None,
+            speed_thresholds: default_speed_thresholds(),
+            font_family: None,
+            ui_scale: default_ui_scale(),
+            display_unit: DisplayUnit::default(),
+            mini_mode: false,
+            low_power_ui: false,
+            audio_cues: false,
+            metrics_port: None,
+            shared_secret: None,
+            log_packets: false,
+            agent_linger_s: None,
+            orphaned: OrphanPolicy::default(),
+            window_size: None,
+            avatar_profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl Configuration {
+    /// These settings with those of `avatar`'s profile in place, if it has one.
+    pub fn for_avatar(&self, avatar: Option<&str>) -> Configuration {
+        let mut config = self.clone();
+        let Some(profile) = avatar.and_then(|avatar| self.avatar_profiles.get(avatar)) else {
+            return config;
+        };
+        if let Some(osc_address) = &profile.osc_address {
+            config.osc_address = osc_address.clone();
+        }
+        if let Some(in_range) = &profile.in_range {
+            config.in_range = in_range.clone();
+        }
+        if let Some(in_center) = profile.in_center {
+            config.in_center = in_center;
+        }
+        if let Some(out_range) = &profile.out_range {
+            config.out_range = out_range.clone();
+        }
+        if let Some(curve_points) = &profile.curve_points {
+            config.curve_points = curve_points.clone();
+        }
+        config
+    }
+}
+
+/// Settings which replace the usual ones while a particular avatar is worn.
+/// Those left out stay as they are.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AvatarProfile {
+    pub osc_address: Option<String>,
+    pub in_range: Option<RangeInclusive<u8>>,
+    pub in_center: Option<u8>,
+    pub out_range: Option<RangeInclusive<f32>>,
+    pub curve_points: Option<Vec<(f32, f32)>>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayUnit {
+    /// Output as 0 to 1 and input as raw flex readings.
+    #[default]
+    Raw,
+    /// Output as 0 to 100% and input as a percentage of the observed range.
+    Percent,
+}
+
+/// When readings are sent over OSC. Intervals are in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResendPolicy {
+    /// Only when the reading changes.
+    OnChange,
+    /// Every interval, whether the reading changed or not.
+    Interval(u32),
+    /// When the reading changes, and again every interval while it doesn't.
+    ChangeOrInterval(u32),
+}
+
+impl Default for ResendPolicy {
+    fn default() -> Self {
+        ResendPolicy::ChangeOrInterval(1000)
+    }
+}
+
+/// Which states reset the avatar to the idle value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdleTrigger {
+    /// Whenever there is no reading, because the Ring-Con is missing or the
+    /// Joy-Con is gone.
+    #[default]
+    NoRingCon,
+    /// Only when the Joy-Con is gone. While it is connected without the
+    /// Ring-Con, the last value sent stays.
+    Disconnected,
+}
+
+/// What the agent does after the window which configured it is gone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrphanPolicy {
+    /// Keep sending readings with the last configuration.
+    #[default]
+    Continue,
+    /// Send the idle value once and nothing more until a window attaches.
+    Pause,
+}
+
+/// What counts as a quick squeeze for `clench_address`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClenchSettings {
+    /// How many flex steps the reading has to rise by.
+    pub min_delta: u8,
+    /// How quickly it has to rise by that much.
+    pub window_ms: u32,
+    /// How long to wait before another squeeze counts.
+    pub cooldown_ms: u32,
+    /// How long the bool stays on.
+    pub pulse_ms: u32,
+}
+
+impl Default for ClenchSettings {
+    fn default() -> Self {
+        Self {
+            min_delta: 6,
+            window_ms: 200,
+            cooldown_ms: 500,
+            pulse_ms: 200,
+        }
+    }
+}
+
+/// Where to send the stick and how to read it, for `stick`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StickSettings {
+    /// Float parameters for left to right and down to up.
+    pub x_address: Option<String>,
+    pub y_address: Option<String>,
+    /// Raw 12 bit readings while the stick is let go.
+    pub center: (u16, u16),
+    /// How far from the center the raw readings go when the stick is pushed
+    /// all the way.
+    pub range: (u16, u16),
+    /// How far from the center the raw readings may wander while the stick
+    /// is let go.
+    pub deadzone: u16,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        Self {
+            x_address: None,
+            y_address: None,
+            center: (2048, 2048),
+            range: (1400, 1400),
+            deadzone: 160,
+        }
+    }
+}
+
+/// Current version of the configuration file format.
+pub const CONFIG_VERSION: u32 = 1;
+pub const UI_SCALE_RANGE: RangeInclusive<f32> = 0.75..=2.0;
+pub const SMOOTHING_TAU_RANGE: RangeInclusive<f32> = 0.0..=1.0;
+
+fn default_discover_port() -> bool {
+    true
+}
+
+fn default_osc_in_port() -> u16 {
+    9001
+}
+
+fn default_auto_start() -> bool {
+    true
+}
+
+fn default_multicast_ttl() -> u32 {
+    1
+}
+
+fn default_reinit_attempts() -> u32 {
+    3
+}
+
+fn default_clutch_ms() -> u32 {
+    1000
+}
+
+fn default_clutch_on() -> u8 {
+    22
+}
+
+fn default_clutch_off() -> u8 {
+    19
+}
+
+fn default_min_packet_interval_ms() -> u32 {
+    50
+}
+
+fn default_warmup_reports() -> u32 {
+    5
+}
+
+fn default_ringcon_grace_ms() -> u32 {
+    1000
+}
+
+fn default_hold_threshold() -> f32 {
+    0.5
+}
+
+fn default_hold_decay_ms() -> u32 {
+    100
+}
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+fn default_speed_thresholds() -> (f32, f32) {
+    (5.0, 30.0)
+}
+
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AvatarProfile, Battery, Configuration, InitializationStep};
+
+    #[test]
+    fn battery_from_report() {
+        assert_eq!(
+            Battery::from_report_byte(0x8e),
+            Battery {
+                percent: 100,
+                charging: false
+            }
+        );
+        assert_eq!(
+            Battery::from_report_byte(0x58),
+            Battery {
+                percent: 50,
+                charging: true
+            }
+        );
+        assert_eq!(
+            Battery::from_report_byte(0x0f),
+            Battery {
+                percent: 0,
+                charging: false
+            }
+        );
+    }
+
+    #[test]
+    fn progress_increases() {
+        let progress: Vec<_> = InitializationStep::ALL
+            .iter()
+            .map(|s| s.progress())
+            .collect();
+        assert_eq!(progress[0], 0.0);
+        assert!(progress.windows(2).all(|w| w[0] < w[1]));
+        assert!(progress[progress.len() - 1] < 1.0);
+    }
+
+    #[test]
+    fn avatar_profile_replaces_its_settings() {
+        let mut config = Configuration::default();
+        config.avatar_profiles.insert(
+            "avtr_a".to_string(),
+            AvatarProfile {
+                osc_address: Some("/avatar/parameters/squeeze".to_string()),
+                in_range: Some(3..=20),
+                ..AvatarProfile::default()
+            },
+        );
+
+        let worn = config.for_avatar(Some("avtr_a"));
+        assert_eq!(worn.osc_address, "/avatar/parameters/squeeze");
+        assert_eq!(worn.in_range, 3..=20);
+        assert_eq!(worn.out_range, config.out_range);
+
+        for other in [Some("avtr_b"), None] {
+            let worn = config.for_avatar(other);
+            assert_eq!(worn.osc_address, config.osc_address);
+            assert_eq!(worn.in_range, config.in_range);
+        }
+    }
 }
@@ -0,0 +1,145 @@
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use osc_ringcon::{messages::Status, osc::SendStats};
+
+/// The latest values exposed on the `/metrics` endpoint.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Metrics {
+    pub flex: Option<u8>,
+    pub state: i32,
+    pub battery_percent: Option<u8>,
+    pub osc: SendStats,
+}
+
+impl Metrics {
+    /// Takes what is worth exposing from a status report.
+    pub fn update(&mut self, status: &Status) {
+        self.state = status.code();
+        match status {
+            Status::Active(reading) => {
+                self.flex = Some(reading.flex);
+                self.battery_percent = Some(reading.battery.percent);
+            }
+            _ => self.flex = None,
+        }
+    }
+
+    /// Formats the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: Option<String>| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            if let Some(value) = value {
+                let _ = writeln!(out, "{} {}", name, value);
+            }
+        };
+        metric(
+            "ringcon_flex",
+            "gauge",
+            "Raw flex reading of the Ring-Con.",
+            self.flex.map(|flex| flex.to_string()),
+        );
+        metric(
+            "ringcon_state",
+            "gauge",
+            "Connection state, using the same codes as status_address.",
+            Some(self.state.to_string()),
+        );
+        metric(
+            "ringcon_battery_percent",
+            "gauge",
+            "Battery level of the Joy-Con.",
+            self.battery_percent.map(|percent| percent.to_string()),
+        );
+        metric(
+            "ringcon_osc_packets_sent_total",
+            "counter",
+            "OSC packets sent.",
+            Some(self.osc.packets.to_string()),
+        );
+        metric(
+            "ringcon_osc_send_errors_total",
+            "counter",
+            "OSC packets which could not be sent.",
+            Some(self.osc.errors.to_string()),
+        );
+        out
+    }
+}
+
+/// Serves `metrics` on `http://127.0.0.1:{port}/metrics` from a background
+/// thread.
+pub(crate) fn serve(port: u16, metrics: Arc<Mutex<Metrics>>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port))?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(err) = respond(stream, &metrics) {
+                eprintln!("Could not serve metrics: {}", err);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(stream: TcpStream, metrics: &Mutex<Metrics>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers aren't needed, but the client expects them to be read.
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut stream = reader.into_inner();
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            metrics.lock().unwrap().render(),
+        ),
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_ringcon::osc::SendStats;
+
+    use super::Metrics;
+
+    #[test]
+    fn missing_readings_have_no_sample() {
+        let metrics = Metrics {
+            flex: None,
+            state: 4,
+            battery_percent: Some(75),
+            osc: SendStats {
+                packets: 12,
+                errors: 1,
+            },
+        };
+        let text = metrics.render();
+        assert!(text.contains("# TYPE ringcon_flex gauge\n#"));
+        assert!(text.contains("\nringcon_state 4\n"));
+        assert!(text.contains("\nringcon_battery_percent 75\n"));
+        assert!(text.contains("\nringcon_osc_packets_sent_total 12\n"));
+        assert!(text.contains("\nringcon_osc_send_errors_total 1\n"));
+    }
+}
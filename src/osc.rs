@@ -0,0 +1,1242 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    sync::mpsc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{
+    mapping::{Bidirectional, MappedOutput},
+    messages::{Configuration, ResendPolicy, Status},
+};
+
+const VRCHAT_PARAMETER_PREFIX: &str = "/avatar/parameters/";
+
+/// Stands for the id of the avatar being worn in `osc_address`.
+pub const AVATAR_PLACEHOLDER: &str = "{avatar}";
+
+/// Suggests a VRChat avatar parameter address when `address` looks like a
+/// mistyped one, e.g. missing the prefix or ending with a slash.
+pub fn suggest_osc_address(address: &str) -> Option<String> {
+    let relative = address.trim().trim_matches('/');
+    let prefix = VRCHAT_PARAMETER_PREFIX.trim_matches('/');
+    let name = match relative.get(..prefix.len()) {
+        Some(start)
+            if start.eq_ignore_ascii_case(prefix)
+                && matches!(relative.as_bytes().get(prefix.len()), None | Some(b'/')) =>
+        {
+            &relative[prefix.len()..]
+        }
+        _ => relative,
+    };
+    let name = name.trim_start_matches('/');
+    if name.is_empty() {
+        return None;
+    }
+
+    let suggestion = format!("{}{}", VRCHAT_PARAMETER_PREFIX, name);
+    if suggestion == address {
+        None
+    } else {
+        Some(suggestion)
+    }
+}
+
+/// Where encoded OSC packets go.
+pub trait Transport {
+    fn send_to(&mut self, packet: &[u8], target: SocketAddr) -> io::Result<()>;
+
+    /// Gets ready to send to `target`, which may be a multicast group that
+    /// should be sent to with `multicast_ttl`.
+    fn prepare(&mut self, _target: SocketAddr, _multicast_ttl: u32) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for UdpSocket {
+    fn send_to(&mut self, packet: &[u8], target: SocketAddr) -> io::Result<()> {
+        UdpSocket::send_to(self, packet, target).map(|_| ())
+    }
+
+    fn prepare(&mut self, target: SocketAddr, multicast_ttl: u32) -> io::Result<()> {
+        // A socket bound to loopback can only reach this computer.
+        let local_ip = match (target.ip().is_loopback(), target) {
+            (true, SocketAddr::V4(_)) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            (true, SocketAddr::V6(_)) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            (false, SocketAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (false, SocketAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        if self.local_addr()?.ip() != local_ip {
+            *self = UdpSocket::bind(SocketAddr::new(local_ip, 0))?;
+        }
+        // std can't set the IPv6 hop limit, so IPv6 groups keep the default of 1.
+        if let SocketAddr::V4(target) = target {
+            if target.ip().is_multicast() {
+                self.set_multicast_ttl_v4(multicast_ttl)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Captures packets instead of sending them, for tests.
+impl Transport for mpsc::Sender<Vec<u8>> {
+    fn send_to(&mut self, packet: &[u8], _target: SocketAddr) -> io::Result<()> {
+        self.send(packet.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
+
+/// Passes packets on to another transport, printing each one first while
+/// `enabled`.
+struct Logged<T> {
+    inner: T,
+    enabled: bool,
+}
+
+impl<T: Transport> Transport for Logged<T> {
+    fn send_to(&mut self, packet: &[u8], target: SocketAddr) -> io::Result<()> {
+        if self.enabled {
+            println!("OSC to {}: {}", target, describe(packet));
+        }
+        self.inner.send_to(packet, target)
+    }
+
+    fn prepare(&mut self, target: SocketAddr, multicast_ttl: u32) -> io::Result<()> {
+        self.inner.prepare(target, multicast_ttl)
+    }
+}
+
+/// Signs packets for a forwarder while it has a secret, and passes them on
+/// to another transport. A signed packet is the OSC packet, then a big
+/// endian 64 bit sequence number which only goes up, then the HMAC-SHA256
+/// of both. The sequence starts from the time in microseconds, so that it
+/// keeps going up when the program is restarted.
+struct Signed<T> {
+    inner: T,
+    key: Option<Hmac<Sha256>>,
+    sequence: u64,
+}
+
+impl<T> Signed<T> {
+    fn set_secret(&mut self, secret: Option<&str>) {
+        self.key = secret.map(|secret| {
+            Hmac::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length")
+        });
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.sequence = self.sequence.max(since_epoch.as_micros() as u64);
+    }
+}
+
+impl<T: Transport> Transport for Signed<T> {
+    fn send_to(&mut self, packet: &[u8], target: SocketAddr) -> io::Result<()> {
+        let Some(key) = &self.key else {
+            return self.inner.send_to(packet, target);
+        };
+        self.sequence += 1;
+        let mut signed = Vec::with_capacity(packet.len() + 8 + 32);
+        signed.extend_from_slice(packet);
+        signed.extend_from_slice(&self.sequence.to_be_bytes());
+        let mut mac = key.clone();
+        mac.update(&signed);
+        signed.extend_from_slice(&mac.finalize().into_bytes());
+        self.inner.send_to(&signed, target)
+    }
+
+    fn prepare(&mut self, target: SocketAddr, multicast_ttl: u32) -> io::Result<()> {
+        self.inner.prepare(target, multicast_ttl)
+    }
+}
+
+/// Decodes a packet as sent by [`OscOut`] into `address type=value` form,
+/// with bools just as their type. Only meant for logging, so anything it
+/// doesn't understand is shown as bytes.
+fn describe(packet: &[u8]) -> String {
+    let malformed = || format!("{:02x?}", packet);
+    let padded = |len: usize| (len + 4) & !3;
+
+    let Some(end) = packet.iter().position(|&b| b == 0) else {
+        return malformed();
+    };
+    let Ok(address) = std::str::from_utf8(&packet[..end]) else {
+        return malformed();
+    };
+    let rest = &packet[padded(end).min(packet.len())..];
+    let Some(tags_end) = rest.iter().position(|&b| b == 0) else {
+        return malformed();
+    };
+    let Some(tags) = rest[..tags_end].strip_prefix(b",") else {
+        return malformed();
+    };
+    let mut args = &rest[padded(tags_end).min(rest.len())..];
+
+    let mut description = address.to_string();
+    for &tag in tags {
+        let arg = match tag {
+            b'f' | b'i' => {
+                let Some((value, remaining)) = args.split_first_chunk::<4>() else {
+                    return malformed();
+                };
+                args = remaining;
+                if tag == b'f' {
+                    format!("f={}", f32::from_be_bytes(*value))
+                } else {
+                    format!("i={}", i32::from_be_bytes(*value))
+                }
+            }
+            b'T' | b'F' => char::from(tag).to_string(),
+            _ => return malformed(),
+        };
+        description.push(' ');
+        description.push_str(&arg);
+    }
+    description
+}
+
+/// The avatar id in an `/avatar/change` message, which VRChat sends when an
+/// avatar is put on.
+fn avatar_change(packet: &[u8]) -> Option<&str> {
+    let arg = packet
+        .strip_prefix(b"/avatar/change\0\0")?
+        .strip_prefix(b",s\0\0")?;
+    let end = arg.iter().position(|&b| b == 0)?;
+    std::str::from_utf8(&arg[..end]).ok()
+}
+
+/// Receives what VRChat sends over OSC about the avatar being worn.
+pub struct OscIn {
+    socket: UdpSocket,
+}
+
+impl OscIn {
+    /// Listens on `port` of this computer, which is where VRChat sends to.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    /// The avatar put on most recently since the last call, without waiting.
+    pub fn avatar_change(&mut self) -> Option<String> {
+        let mut buf = [0; 1024];
+        let mut avatar = None;
+        loop {
+            match self.socket.recv(&mut buf) {
+                Ok(len) => {
+                    if let Some(id) = avatar_change(&buf[..len]) {
+                        avatar = Some(id.to_string());
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    eprintln!("Could not receive OSC {:?}", err);
+                    break;
+                }
+            }
+        }
+        avatar
+    }
+}
+
+/// An OSC message with a single 32 bit argument, encoded once so that
+/// sending only has to fill in the value.
+struct Message {
+    buffer: Vec<u8>,
+}
+
+impl Message {
+    #[cfg(not(feature = "rosc"))]
+    fn float(address: &str) -> Self {
+        Self::new(address, b'f')
+    }
+
+    #[cfg(not(feature = "rosc"))]
+    fn int(address: &str) -> Self {
+        Self::new(address, b'i')
+    }
+
+    #[cfg(feature = "rosc")]
+    fn float(address: &str) -> Self {
+        Self {
+            buffer: encode_with_rosc(address, rosc::OscType::Float(0.0)),
+        }
+    }
+
+    #[cfg(feature = "rosc")]
+    fn int(address: &str) -> Self {
+        Self {
+            buffer: encode_with_rosc(address, rosc::OscType::Int(0)),
+        }
+    }
+
+    #[cfg_attr(feature = "rosc", allow(dead_code))]
+    fn new(address: &str, type_tag: u8) -> Self {
+        // null terminated address string, padded to 4 byte boundaries,
+        // followed by type code and argument.
+        let mut buffer = Vec::with_capacity(((address.len() + 4) & !3) + 8);
+        buffer.extend_from_slice(address.as_bytes());
+        buffer.push(0);
+        let align = ((buffer.len() - 1 + 4) & !3) - buffer.len();
+        buffer.extend_from_slice(&[0, 0, 0][..align]);
+        buffer.extend_from_slice(&[b',', type_tag, 0, 0, 0, 0, 0, 0]);
+        Self { buffer }
+    }
+
+    fn same_address(&self, other: &Message) -> bool {
+        // Everything but the argument.
+        self.buffer[..self.buffer.len() - 4] == other.buffer[..other.buffer.len() - 4]
+    }
+
+    fn with_value(&mut self, value: [u8; 4]) -> &[u8] {
+        let range = self.buffer.len() - 4..;
+        self.buffer[range].copy_from_slice(&value);
+        &self.buffer
+    }
+
+    fn with_float(&mut self, value: f32) -> &[u8] {
+        self.with_value(value.to_be_bytes())
+    }
+
+    fn with_int(&mut self, value: i32) -> &[u8] {
+        self.with_value(value.to_be_bytes())
+    }
+}
+
+/// An OSC message with a single bool argument. OSC puts bools in the type
+/// tag rather than the arguments, so both versions are encoded up front.
+struct BoolMessage {
+    on: Vec<u8>,
+    off: Vec<u8>,
+}
+
+impl BoolMessage {
+    #[cfg(feature = "rosc")]
+    fn new(address: &str) -> Self {
+        Self {
+            on: encode_with_rosc(address, rosc::OscType::Bool(true)),
+            off: encode_with_rosc(address, rosc::OscType::Bool(false)),
+        }
+    }
+
+    #[cfg(not(feature = "rosc"))]
+    fn new(address: &str) -> Self {
+        let encode = |type_tag| {
+            let mut buffer = Message::new(address, type_tag).buffer;
+            // Drop the argument, which bools don't have.
+            buffer.truncate(buffer.len() - 4);
+            buffer
+        };
+        Self {
+            on: encode(b'T'),
+            off: encode(b'F'),
+        }
+    }
+
+    fn with_bool(&self, value: bool) -> &[u8] {
+        if value {
+            &self.on
+        } else {
+            &self.off
+        }
+    }
+}
+
+/// Encodes a message with a single argument using rosc. Only used to build
+/// the templates above, so sending still just fills in the value.
+#[cfg(feature = "rosc")]
+fn encode_with_rosc(address: &str, arg: rosc::OscType) -> Vec<u8> {
+    let packet = rosc::OscPacket::Message(rosc::OscMessage {
+        addr: address.to_string(),
+        args: vec![arg],
+    });
+    rosc::encoder::encode(&packet).expect("OSC message with one argument")
+}
+
+/// Rounds `value` to the nearest of `steps` equal steps between -1 and 1,
+/// like VRChat does for synced float parameters.
+fn quantize(value: f32, steps: u32) -> f32 {
+    let step = 2.0 / steps as f32;
+    (value / step).round() * step
+}
+
+/// Counts of packets handed to the transport since the [`OscOut`] was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SendStats {
+    pub packets: u64,
+    pub errors: u64,
+}
+
+impl SendStats {
+    fn record(&mut self, result: io::Result<()>) {
+        match result {
+            Ok(()) => self.packets += 1,
+            Err(err) => {
+                // Reported once, so that an unreachable target doesn't
+                // flood the log with a line per reading.
+                if self.errors == 0 {
+                    eprintln!("Could not send OSC packet: {}", err);
+                }
+                self.errors += 1;
+            }
+        }
+    }
+}
+
+/// Sends mapped Ring-Con readings as OSC float messages.
+pub struct OscOut<T = UdpSocket> {
+    transport: Logged<Signed<T>>,
+    target: SocketAddr,
+    message: Option<Message>,
+    complementary: Option<(Message, Message)>,
+    speed_category: Option<Message>,
+    bidirectional: Option<(Message, Bidirectional)>,
+    calibration_needed: Option<BoolMessage>,
+    clench: Option<BoolMessage>,
+    clutch: Option<BoolMessage>,
+    stick_x: Option<Message>,
+    stick_y: Option<Message>,
+    status: Option<Message>,
+    /// The last status code sent, so that it is only sent when it changes.
+    status_sent: Option<i32>,
+    quantize_steps: Option<u32>,
+    resend: ResendPolicy,
+    /// The last output sent and when, for skipping quantized repeats.
+    last_sent: Option<(MappedOutput, Instant)>,
+    min_interval: Duration,
+    /// When readings were last sent, for keeping to `min_interval`.
+    sent_at: Option<Instant>,
+    /// The latest reading which came too soon after the last one sent.
+    pending: Option<MappedOutput>,
+    /// Something other than the idle value was last sent to `message` or
+    /// `complementary`, so the parameters would be left where they are if
+    /// those changed.
+    holding: bool,
+    out_idle: f32,
+    /// The avatar being worn, for filling in [`AVATAR_PLACEHOLDER`].
+    avatar: Option<String>,
+    stats: SendStats,
+}
+
+impl OscOut {
+    pub fn new() -> Self {
+        Self::with_transport(
+            UdpSocket::bind(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0))).unwrap(),
+        )
+    }
+}
+
+impl Default for OscOut {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Transport> OscOut<T> {
+    pub fn with_transport(transport: T) -> Self {
+        Self {
+            transport: Logged {
+                inner: Signed {
+                    inner: transport,
+                    key: None,
+                    sequence: 0,
+                },
+                enabled: false,
+            },
+            target: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)),
+            message: None,
+            complementary: None,
+            speed_category: None,
+            bidirectional: None,
+            calibration_needed: None,
+            clench: None,
+            clutch: None,
+            stick_x: None,
+            stick_y: None,
+            status: None,
+            status_sent: None,
+            quantize_steps: None,
+            resend: ResendPolicy::default(),
+            last_sent: None,
+            min_interval: Duration::ZERO,
+            sent_at: None,
+            pending: None,
+            holding: false,
+            out_idle: 0.0,
+            avatar: None,
+            stats: SendStats::default(),
+        }
+    }
+
+    /// Sets the avatar whose id replaces [`AVATAR_PLACEHOLDER`], from the
+    /// next [`configure`](Self::configure) on.
+    pub fn set_avatar(&mut self, avatar: Option<&str>) {
+        self.avatar = avatar.map(str::to_string);
+    }
+
+    pub fn configure(&mut self, config: &Configuration) {
+        let message = match &self.avatar {
+            Some(avatar) => Message::float(&config.osc_address.replace(AVATAR_PLACEHOLDER, avatar)),
+            // Sent as written until it is known which avatar is worn.
+            None => Message::float(&config.osc_address),
+        };
+        let complementary = config
+            .complementary
+            .as_ref()
+            .map(|(a, b)| (Message::float(a), Message::float(b)));
+        if self.holding
+            && (config.udp_address != self.target
+                || !self.sends_to(&message, complementary.as_ref()))
+        {
+            // Put the old parameters back before switching to the new ones.
+            self.reset_parameters();
+            self.holding = false;
+        }
+        self.out_idle = config.out_idle;
+        self.transport.enabled = config.log_packets;
+        self.transport
+            .inner
+            .set_secret(config.shared_secret.as_deref());
+
+        self.target = config.udp_address;
+        if let Err(err) = self.transport.prepare(self.target, config.multicast_ttl) {
+            eprintln!("Could not prepare to send to {}: {}", self.target, err);
+        }
+
+        self.message = Some(message);
+        self.complementary = complementary;
+        self.speed_category = config.speed_category_address.as_deref().map(Message::int);
+        self.bidirectional = config
+            .bidirectional_address
+            .as_deref()
+            .map(|address| (Message::float(address), Bidirectional::new(config)));
+        self.calibration_needed = config
+            .calibration_needed_address
+            .as_deref()
+            .map(BoolMessage::new);
+        self.clench = config.clench_address.as_deref().map(BoolMessage::new);
+        self.clutch = config.clutch_address.as_deref().map(BoolMessage::new);
+        let stick = config.stick.as_ref();
+        self.stick_x = stick
+            .and_then(|stick| stick.x_address.as_deref())
+            .map(Message::float);
+        self.stick_y = stick
+            .and_then(|stick| stick.y_address.as_deref())
+            .map(Message::float);
+        self.status = config.status_address.as_deref().map(Message::int);
+        // The address or target may have changed, so send the status again.
+        self.status_sent = None;
+        self.quantize_steps = config.quantize_steps.filter(|&steps| steps > 0);
+        self.resend = config.resend;
+        self.last_sent = None;
+        self.min_interval = Duration::from_millis(config.min_packet_interval_ms.into());
+    }
+
+    /// Whether the output already goes to the addresses it would go to with
+    /// `message` and `complementary`.
+    fn sends_to(&self, message: &Message, complementary: Option<&(Message, Message)>) -> bool {
+        match (&self.complementary, complementary) {
+            (Some((first, second)), Some((new_first, new_second))) => {
+                first.same_address(new_first) && second.same_address(new_second)
+            }
+            (None, None) => self
+                .message
+                .as_ref()
+                .is_some_and(|old| old.same_address(message)),
+            _ => false,
+        }
+    }
+
+    /// Sends the idle value to wherever the output went.
+    fn reset_parameters(&mut self) {
+        if let Some((first, second)) = &mut self.complementary {
+            // The idle value isn't complemented.
+            self.stats.record(
+                self.transport
+                    .send_to(first.with_float(self.out_idle), self.target),
+            );
+            self.stats.record(
+                self.transport
+                    .send_to(second.with_float(self.out_idle), self.target),
+            );
+        } else if let Some(message) = &mut self.message {
+            self.stats.record(
+                self.transport
+                    .send_to(message.with_float(self.out_idle), self.target),
+            );
+        }
+    }
+
+    /// Sends `output`, or keeps it for [`flush`](Self::flush) if it comes
+    /// too soon after the last one sent before `now`. The idle value is
+    /// always sent right away.
+    pub fn send(&mut self, output: &MappedOutput, now: Instant) {
+        let too_soon = self
+            .sent_at
+            .is_some_and(|at| now.duration_since(at) < self.min_interval);
+        if too_soon && !output.is_idle() {
+            self.pending = Some(*output);
+            return;
+        }
+        self.pending = None;
+        self.sent_at = Some(now);
+        self.send_now(output, now);
+    }
+
+    /// Sends the reading held back by [`send`](Self::send), once enough time
+    /// has passed by `now`.
+    pub fn flush(&mut self, now: Instant) {
+        if let Some(output) = self.pending {
+            self.send(&output, now);
+        }
+    }
+
+    fn send_now(&mut self, output: &MappedOutput, now: Instant) {
+        if self.message.is_none() {
+            return;
+        }
+
+        let output = match self.quantize_steps {
+            // The idle value is sent as configured.
+            Some(steps) if !output.is_idle() => {
+                let output = MappedOutput {
+                    value: quantize(output.value, steps),
+                    ..*output
+                };
+                if self.is_repeat(&output, now) {
+                    return;
+                }
+                self.last_sent = Some((output, now));
+                output
+            }
+            _ => {
+                // Whatever comes after the idle value is a change.
+                self.last_sent = None;
+                *output
+            }
+        };
+        let message = self.message.as_mut().unwrap();
+
+        let value = output.value;
+        self.holding = !output.is_idle();
+        if let Some((first, second)) = &mut self.complementary {
+            // The idle value means "no reading", so it isn't complemented.
+            let complement = if output.is_idle() { value } else { 1.0 - value };
+            self.stats
+                .record(self.transport.send_to(first.with_float(value), self.target));
+            self.stats.record(
+                self.transport
+                    .send_to(second.with_float(complement), self.target),
+            );
+        } else {
+            self.stats.record(
+                self.transport
+                    .send_to(message.with_float(value), self.target),
+            );
+        }
+        if let Some(speed_category) = &mut self.speed_category {
+            self.stats.record(
+                self.transport
+                    .send_to(speed_category.with_int(output.speed as i32), self.target),
+            );
+        }
+        if let Some((message, bidirectional)) = &mut self.bidirectional {
+            let value = bidirectional.value(output.flex);
+            self.stats.record(
+                self.transport
+                    .send_to(message.with_float(value), self.target),
+            );
+        }
+        if let Some(calibration_needed) = &self.calibration_needed {
+            self.stats.record(self.transport.send_to(
+                calibration_needed.with_bool(output.calibration_needed),
+                self.target,
+            ));
+        }
+        if let Some(clench) = &self.clench {
+            self.stats.record(
+                self.transport
+                    .send_to(clench.with_bool(output.clench), self.target),
+            );
+        }
+        if let Some(clutch) = &self.clutch {
+            self.stats.record(
+                self.transport
+                    .send_to(clutch.with_bool(output.clutch), self.target),
+            );
+        }
+        // Without a reading the stick is centered.
+        let (x, y) = output.stick.unwrap_or((0.0, 0.0));
+        if let Some(stick_x) = &mut self.stick_x {
+            self.stats
+                .record(self.transport.send_to(stick_x.with_float(x), self.target));
+        }
+        if let Some(stick_y) = &mut self.stick_y {
+            self.stats
+                .record(self.transport.send_to(stick_y.with_float(y), self.target));
+        }
+    }
+
+    /// How many packets were sent and how many could not be.
+    pub fn stats(&self) -> SendStats {
+        self.stats
+    }
+
+    /// Whether `output` only repeats the last output sent, before the resend
+    /// policy asks for it to be repeated.
+    fn is_repeat(&self, output: &MappedOutput, now: Instant) -> bool {
+        let Some((last, sent)) = &self.last_sent else {
+            return false;
+        };
+        // The flex may change without changing anything that is sent.
+        let same = last.value == output.value
+            && last.speed == output.speed
+            && last.calibration_needed == output.calibration_needed
+            && last.clench == output.clench
+            && last.clutch == output.clutch
+            && last.stick == output.stick;
+        let resend_after = match self.resend {
+            ResendPolicy::OnChange => None,
+            ResendPolicy::Interval(ms) | ResendPolicy::ChangeOrInterval(ms) => {
+                Some(Duration::from_millis(ms.into()))
+            }
+        };
+        same && resend_after.is_none_or(|interval| now.duration_since(*sent) < interval)
+    }
+
+    /// Sends the status code to `status_address` if it changed.
+    pub fn send_status(&mut self, status: &Status) {
+        let message = match &mut self.status {
+            Some(message) => message,
+            None => return,
+        };
+
+        let code = status.code();
+        if self.status_sent == Some(code) {
+            return;
+        }
+        self.stats
+            .record(self.transport.send_to(message.with_int(code), self.target));
+        self.status_sent = Some(code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use super::{
+        avatar_change, describe, quantize, suggest_osc_address, BoolMessage, Message, OscIn,
+        OscOut, SendStats,
+    };
+    use crate::{
+        mapping::MappedOutput,
+        messages::{Configuration, InitializationStep, ResendPolicy, Status},
+        speed::SpeedCategory,
+    };
+
+    fn config() -> Configuration {
+        Configuration {
+            discover_port: false,
+            idle_poll_ms: None,
+            reinit_attempts: 0,
+            min_packet_interval_ms: 0,
+            ..Configuration::default()
+        }
+    }
+
+    fn packet(value: f32) -> Vec<u8> {
+        let mut packet = b"/avatar/parameters/ringcon_flex\0,f\0\0".to_vec();
+        packet.extend_from_slice(&value.to_be_bytes());
+        packet
+    }
+
+    fn message(address: &[u8], value: f32) -> Vec<u8> {
+        let mut message = address.to_vec();
+        message.extend_from_slice(&value.to_be_bytes());
+        message
+    }
+
+    fn output(flex: u8, value: f32) -> MappedOutput {
+        MappedOutput {
+            flex,
+            value,
+            speed: SpeedCategory::Still,
+            calibration_needed: false,
+            clench: false,
+            clutch: false,
+            stick: None,
+        }
+    }
+
+    #[test]
+    fn describe_decodes_what_is_sent() {
+        assert_eq!(
+            describe(Message::float("/a/flex").with_float(0.5)),
+            "/a/flex f=0.5"
+        );
+        assert_eq!(describe(Message::int("/abc").with_int(-2)), "/abc i=-2");
+        assert_eq!(describe(BoolMessage::new("/on").with_bool(true)), "/on T");
+
+        let packet = Message::float("/a/flex").with_float(0.5).to_vec();
+        assert!(describe(&packet[..packet.len() - 1]).starts_with('['));
+        assert!(describe(b"/a").starts_with('['));
+    }
+
+    #[test]
+    fn avatar_change_is_read() {
+        let packet = b"/avatar/change\0\0,s\0\0avtr_1234\0\0\0";
+        assert_eq!(avatar_change(packet), Some("avtr_1234"));
+        assert_eq!(avatar_change(b"/avatar/change\0\0,f\0\0\0\0\0\0"), None);
+        assert_eq!(
+            avatar_change(Message::float("/avatar/change").with_float(1.0)),
+            None
+        );
+        assert_eq!(avatar_change(&packet[..packet.len() - 4]), None);
+
+        let mut osc_in = OscIn::bind(0).unwrap();
+        let port = osc_in.socket.local_addr().unwrap().port();
+        let sender = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+        sender
+            .send_to(b"/other\0\0,\0\0\0", ("127.0.0.1", port))
+            .unwrap();
+        sender.send_to(packet, ("127.0.0.1", port)).unwrap();
+        assert_eq!(osc_in.avatar_change().as_deref(), Some("avtr_1234"));
+        assert_eq!(osc_in.avatar_change(), None);
+    }
+
+    #[test]
+    fn address_padding() {
+        for len in 0..20 {
+            let address = "a".repeat(len);
+            let packet = Message::float(&address).with_float(1.0).to_vec();
+
+            // The address is null terminated and padded to 4 bytes.
+            let padded = (len / 4 + 1) * 4;
+            assert_eq!(packet.len(), padded + 8, "length {}", len);
+            assert_eq!(&packet[..len], address.as_bytes(), "length {}", len);
+            assert!(
+                packet[len..padded].iter().all(|&b| b == 0),
+                "length {}",
+                len
+            );
+            assert_eq!(&packet[padded..padded + 4], b",f\0\0", "length {}", len);
+            assert_eq!(
+                &packet[padded + 4..],
+                1.0f32.to_be_bytes(),
+                "length {}",
+                len
+            );
+        }
+    }
+
+    #[cfg(feature = "rosc")]
+    #[test]
+    fn rosc_matches_hand_rolled() {
+        for len in 0..20 {
+            let address = "a".repeat(len);
+            let hand_rolled = Message::new(&address, b'f').with_float(0.5).to_vec();
+            let rosc = Message::float(&address).with_float(0.5).to_vec();
+            assert_eq!(rosc, hand_rolled, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn nothing_sent_before_configure() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.send(&output(15, 0.75), Instant::now());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn send_encodes_value() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&config());
+
+        for value in [0.0, 0.5, -1.0, 2.0] {
+            osc_out.send(&output(15, value), Instant::now());
+            assert_eq!(rx.try_recv().unwrap(), packet(value));
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn packets_are_signed_with_secret() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&Configuration {
+            shared_secret: Some("secret".to_string()),
+            ..config()
+        });
+
+        osc_out.send(&output(15, 0.5), Instant::now());
+        osc_out.send(&output(16, 0.75), Instant::now());
+        let sent: Vec<_> = rx.try_iter().collect();
+        let mut sequences = Vec::new();
+        for (signed, expected) in sent.iter().zip([packet(0.5), packet(0.75)]) {
+            let (signed, tag) = signed.split_at(signed.len() - 32);
+            let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+            mac.update(signed);
+            mac.verify_slice(tag).unwrap();
+            let (osc, sequence) = signed.split_at(signed.len() - 8);
+            assert_eq!(osc, expected);
+            sequences.push(u64::from_be_bytes(sequence.try_into().unwrap()));
+        }
+        assert!(sequences[0] < sequences[1]);
+    }
+
+    #[test]
+    fn send_errors_are_counted() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&config());
+
+        osc_out.send(&output(15, 0.5), Instant::now());
+        drop(rx);
+        osc_out.send(&output(15, 0.75), Instant::now());
+        osc_out.send(&output(15, 1.0), Instant::now());
+        assert_eq!(
+            osc_out.stats(),
+            SendStats {
+                packets: 1,
+                errors: 2
+            }
+        );
+    }
+
+    #[test]
+    fn readings_keep_to_min_interval() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        let config = Configuration {
+            min_packet_interval_ms: 50,
+            ..config()
+        };
+        osc_out.configure(&config);
+
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        osc_out.send(&output(10, 0.25), at(0));
+        osc_out.send(&output(11, 0.5), at(10));
+        osc_out.send(&output(12, 0.75), at(20));
+        osc_out.flush(at(49));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [packet(0.25)]);
+
+        osc_out.flush(at(50));
+        osc_out.flush(at(60));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [packet(0.75)]);
+
+        // The idle value doesn't wait, and replaces what was held back.
+        osc_out.send(&output(13, 1.0), at(70));
+        osc_out.send(&MappedOutput::idle(&config), at(80));
+        osc_out.flush(at(200));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [packet(0.0)]);
+    }
+
+    #[test]
+    fn new_address_resets_old_one() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&config());
+        osc_out.send(&output(15, 0.75), Instant::now());
+        rx.try_recv().unwrap();
+
+        osc_out.configure(&Configuration {
+            osc_address: "/a".to_string(),
+            out_idle: 0.25,
+            ..config()
+        });
+        // The idle value of the settings which were in use.
+        assert_eq!(rx.try_recv().unwrap(), packet(0.0));
+        assert!(rx.try_recv().is_err());
+
+        // Only once, and not after the idle value was sent anyway.
+        osc_out.send(&output(0, 0.25), Instant::now());
+        osc_out.configure(&config());
+        rx.try_recv().unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn unmoved_output_stays_held() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&config());
+        osc_out.send(&output(15, 0.75), Instant::now());
+        rx.try_recv().unwrap();
+
+        // Other settings changing doesn't reset the parameter, nor forget
+        // that it still has to be reset once the address changes.
+        osc_out.configure(&Configuration {
+            gain: 2.0,
+            ..config()
+        });
+        assert!(rx.try_recv().is_err());
+        osc_out.configure(&Configuration {
+            osc_address: "/a".to_string(),
+            ..config()
+        });
+        assert_eq!(rx.try_recv().unwrap(), packet(0.0));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn new_address_resets_complementary_pair() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        let pair = Configuration {
+            complementary: Some(("/smile".to_string(), "/frown".to_string())),
+            ..config()
+        };
+        osc_out.configure(&pair);
+        osc_out.send(&output(15, 0.75), Instant::now());
+        rx.try_iter().for_each(drop);
+
+        osc_out.configure(&pair);
+        assert!(rx.try_recv().is_err());
+
+        // The pair is what was sent to, not `osc_address`.
+        osc_out.configure(&config());
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            [
+                message(b"/smile\0\0,f\0\0", 0.0),
+                message(b"/frown\0\0,f\0\0", 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn reconfigure_changes_address() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&config());
+        osc_out.configure(&Configuration {
+            osc_address: "/a".to_string(),
+            ..config()
+        });
+
+        osc_out.send(&output(15, 0.75), Instant::now());
+        let mut expected = b"/a\0\0,f\0\0".to_vec();
+        expected.extend_from_slice(&0.75f32.to_be_bytes());
+        assert_eq!(rx.try_recv().unwrap(), expected);
+    }
+
+    #[test]
+    fn avatar_id_fills_in_address() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        let config = Configuration {
+            osc_address: "/{avatar}".to_string(),
+            ..config()
+        };
+        osc_out.configure(&config);
+        osc_out.send(&output(0, 0.0), Instant::now());
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            message(b"/{avatar}\0\0\0,f\0\0", 0.0)
+        );
+
+        osc_out.set_avatar(Some("avtr_1"));
+        osc_out.configure(&config);
+        osc_out.send(&output(0, 0.0), Instant::now());
+        assert_eq!(rx.try_recv().unwrap(), message(b"/avtr_1\0,f\0\0", 0.0));
+    }
+
+    #[test]
+    fn multicast_target() {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut osc_out = OscOut::with_transport(socket);
+        osc_out.configure(&Configuration {
+            udp_address: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(239, 0, 0, 1), 9000)),
+            multicast_ttl: 4,
+            ..config()
+        });
+
+        let local = osc_out.transport.inner.inner.local_addr().unwrap();
+        assert_eq!(local.ip(), Ipv4Addr::UNSPECIFIED);
+        assert_eq!(osc_out.transport.inner.inner.multicast_ttl_v4().unwrap(), 4);
+
+        osc_out.configure(&config());
+        let local = osc_out.transport.inner.inner.local_addr().unwrap();
+        assert_eq!(local.ip(), Ipv4Addr::LOCALHOST);
+    }
+
+    #[test]
+    fn suggestions() {
+        let cases = [
+            ("/avatar/parameters/ringcon_flex", None),
+            ("ringcon_flex", Some("/avatar/parameters/ringcon_flex")),
+            ("/ringcon_flex", Some("/avatar/parameters/ringcon_flex")),
+            (
+                "/avatar/parameters/ringcon_flex/",
+                Some("/avatar/parameters/ringcon_flex"),
+            ),
+            (
+                "avatar/parameters/ringcon_flex",
+                Some("/avatar/parameters/ringcon_flex"),
+            ),
+            (
+                "/Avatar/Parameters/ringcon_flex",
+                Some("/avatar/parameters/ringcon_flex"),
+            ),
+            (
+                " /avatar/parameters/ringcon_flex ",
+                Some("/avatar/parameters/ringcon_flex"),
+            ),
+            ("/avatar/parameters/", None),
+            ("", None),
+        ];
+        for (address, expected) in cases {
+            assert_eq!(
+                suggest_osc_address(address).as_deref(),
+                expected,
+                "address {:?}",
+                address
+            );
+        }
+    }
+
+    #[test]
+    fn complementary_pair() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&Configuration {
+            complementary: Some(("/smile".to_string(), "/frown".to_string())),
+            ..config()
+        });
+
+        osc_out.send(&output(1, 1.0), Instant::now());
+        osc_out.send(&output(0, 0.0), Instant::now());
+        let sent: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            sent,
+            [
+                message(b"/smile\0\0,f\0\0", 1.0),
+                message(b"/frown\0\0,f\0\0", 0.0),
+                message(b"/smile\0\0,f\0\0", 0.0),
+                message(b"/frown\0\0,f\0\0", 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn speed_category() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&Configuration {
+            speed_category_address: Some("/speed".to_string()),
+            ..config()
+        });
+
+        osc_out.send(
+            &MappedOutput {
+                speed: SpeedCategory::Fast,
+                ..output(15, 0.75)
+            },
+            Instant::now(),
+        );
+        let sent: Vec<_> = rx.try_iter().collect();
+        let mut expected = b"/speed\0\0,i\0\0".to_vec();
+        expected.extend_from_slice(&2i32.to_be_bytes());
+        assert_eq!(sent, [packet(0.75), expected]);
+    }
+
+    #[test]
+    fn calibration_needed() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&Configuration {
+            calibration_needed_address: Some("/calibrate".to_string()),
+            ..config()
+        });
+
+        osc_out.send(
+            &MappedOutput {
+                calibration_needed: true,
+                ..output(15, 0.75)
+            },
+            Instant::now(),
+        );
+        osc_out.send(&output(15, 0.75), Instant::now());
+        let sent: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            sent,
+            [
+                packet(0.75),
+                b"/calibrate\0\0,T\0\0".to_vec(),
+                packet(0.75),
+                b"/calibrate\0\0,F\0\0".to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn status_sent_on_change() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&Configuration {
+            status_address: Some("/state".to_string()),
+            ..config()
+        });
+
+        fn message(code: i32) -> Vec<u8> {
+            let mut message = b"/state\0\0,i\0\0".to_vec();
+            message.extend_from_slice(&code.to_be_bytes());
+            message
+        }
+
+        osc_out.send_status(&Status::NotConnected);
+        osc_out.send_status(&Status::Initializing(InitializationStep::Configuring));
+        osc_out.send_status(&Status::Initializing(InitializationStep::McuState));
+        osc_out.send_status(&Status::NoRingCon);
+        osc_out.send_status(&Status::NoRingCon);
+        osc_out.send_status(&Status::Disconnected);
+        let sent: Vec<_> = rx.try_iter().collect();
+        assert_eq!(sent, [message(0), message(1), message(2), message(4)]);
+    }
+
+    #[test]
+    fn quantized_repeats_are_skipped() {
+        let (tx, rx) = mpsc::channel();
+        let mut osc_out = OscOut::with_transport(tx);
+        osc_out.configure(&Configuration {
+            quantize_steps: Some(256),
+            resend: ResendPolicy::OnChange,
+            ..config()
+        });
+
+        assert_eq!(quantize(0.751, 256), 0.75);
+        osc_out.send(&output(15, 0.75), Instant::now());
+        osc_out.send(&output(15, 0.751), Instant::now());
+        osc_out.send(&output(16, 0.7501), Instant::now());
+        osc_out.send(&output(17, 0.76), Instant::now());
+        osc_out.send(&output(0, 0.0), Instant::now());
+        osc_out.send(&output(0, 0.0), Instant::now());
+        osc_out.send(&output(17, 0.76), Instant::now());
+        let sent: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            sent,
+            [
+                packet(0.75),
+                packet(quantize(0.76, 256)),
+                packet(0.0),
+                packet(0.0),
+                packet(quantize(0.76, 256)),
+            ]
+        );
+    }
+}
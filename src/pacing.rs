@@ -0,0 +1,186 @@
+use std::time::{Duration, Instant};
+
+use crate::messages::ResendPolicy;
+
+/// Longest time the status goes without being reported, whatever
+/// [`ResendPolicy`] is used for OSC. The agent's watchdog relies on this.
+pub(crate) const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the Ring-Con must be missing before polling slows down.
+pub(crate) const IDLE_DELAY: Duration = Duration::from_secs(5);
+
+/// How far the reading may wander while the Ring-Con counts as set down.
+pub(crate) const STILL_TOLERANCE: u8 = 1;
+
+/// Keeps track of where the reading has stayed, within [`STILL_TOLERANCE`],
+/// and since when.
+pub(crate) fn track_stillness(
+    prev: Option<(u8, Instant)>,
+    flex: u8,
+    now: Instant,
+) -> (u8, Instant) {
+    match prev {
+        Some((still_at, since)) if flex.abs_diff(still_at) <= STILL_TOLERANCE => (still_at, since),
+        _ => (flex, now),
+    }
+}
+
+/// Decides whether a reading should be sent, given the last one that was.
+pub(crate) fn should_send(
+    policy: ResendPolicy,
+    prev: Option<(u8, Instant)>,
+    flex: u8,
+    now: Instant,
+) -> bool {
+    let Some((prev_flex, prev_time)) = prev else {
+        return true;
+    };
+    let changed = prev_flex != flex;
+    let elapsed = |ms: u32| now.duration_since(prev_time) >= Duration::from_millis(ms.into());
+    match policy {
+        ResendPolicy::OnChange => changed,
+        ResendPolicy::Interval(ms) => elapsed(ms),
+        ResendPolicy::ChangeOrInterval(ms) => changed || elapsed(ms),
+    }
+}
+
+/// Decides whether the status should be reported: when the reading changes,
+/// and at least every [`STATUS_INTERVAL`].
+pub(crate) fn should_report(prev: Option<(u8, Instant)>, flex: u8, now: Instant) -> bool {
+    match prev {
+        Some((prev_flex, prev_time)) => {
+            prev_flex != flex || now.duration_since(prev_time) >= STATUS_INTERVAL
+        }
+        None => true,
+    }
+}
+
+/// How long to pause before the next read, given when the Ring-Con went
+/// missing and the configured idle polling interval.
+pub(crate) fn idle_pause(
+    missing_since: Option<Instant>,
+    now: Instant,
+    interval: Option<Duration>,
+) -> Option<Duration> {
+    let missing_since = missing_since?;
+    if now.duration_since(missing_since) >= IDLE_DELAY {
+        interval
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{
+        idle_pause, should_report, should_send, track_stillness, IDLE_DELAY, STATUS_INTERVAL,
+    };
+    use crate::messages::ResendPolicy;
+
+    const INTERVAL: Duration = Duration::from_millis(1000);
+    const DEFAULT: ResendPolicy = ResendPolicy::ChangeOrInterval(1000);
+
+    #[test]
+    fn first_reading_is_sent() {
+        for policy in [
+            DEFAULT,
+            ResendPolicy::OnChange,
+            ResendPolicy::Interval(1000),
+        ] {
+            assert!(should_send(policy, None, 15, Instant::now()));
+            assert!(should_send(policy, None, 0, Instant::now()));
+        }
+    }
+
+    #[test]
+    fn changes_are_sent() {
+        let now = Instant::now();
+        assert!(should_send(DEFAULT, Some((15, now)), 16, now));
+        assert!(should_send(DEFAULT, Some((15, now)), 0, now));
+    }
+
+    #[test]
+    fn repeats_are_held_back() {
+        let start = Instant::now();
+        assert!(!should_send(DEFAULT, Some((15, start)), 15, start));
+        assert!(!should_send(
+            DEFAULT,
+            Some((15, start)),
+            15,
+            start + INTERVAL - Duration::from_millis(1)
+        ));
+        assert!(should_send(
+            DEFAULT,
+            Some((15, start)),
+            15,
+            start + INTERVAL
+        ));
+    }
+
+    #[test]
+    fn on_change_never_repeats() {
+        let start = Instant::now();
+        let policy = ResendPolicy::OnChange;
+        assert!(should_send(policy, Some((15, start)), 16, start));
+        assert!(!should_send(
+            policy,
+            Some((15, start)),
+            15,
+            start + INTERVAL * 10
+        ));
+    }
+
+    #[test]
+    fn interval_ignores_changes() {
+        let start = Instant::now();
+        let policy = ResendPolicy::Interval(100);
+        let interval = Duration::from_millis(100);
+        assert!(!should_send(policy, Some((15, start)), 16, start));
+        assert!(!should_send(
+            policy,
+            Some((15, start)),
+            16,
+            start + interval - Duration::from_millis(1)
+        ));
+        assert!(should_send(policy, Some((15, start)), 15, start + interval));
+    }
+
+    #[test]
+    fn status_is_reported() {
+        let start = Instant::now();
+        assert!(should_report(None, 15, start));
+        assert!(should_report(Some((15, start)), 16, start));
+        assert!(!should_report(Some((15, start)), 15, start));
+        assert!(should_report(
+            Some((15, start)),
+            15,
+            start + STATUS_INTERVAL
+        ));
+    }
+
+    #[test]
+    fn idle_pause_after_delay() {
+        let start = Instant::now();
+        let interval = Some(Duration::from_millis(250));
+        assert_eq!(idle_pause(None, start + IDLE_DELAY, interval), None);
+        assert_eq!(idle_pause(Some(start), start, interval), None);
+        assert_eq!(
+            idle_pause(Some(start), start + IDLE_DELAY, interval),
+            interval
+        );
+        assert_eq!(idle_pause(Some(start), start + IDLE_DELAY, None), None);
+    }
+
+    #[test]
+    fn small_wobbles_stay_still() {
+        let start = Instant::now();
+        let later = start + INTERVAL;
+        let still = track_stillness(None, 15, start);
+        assert_eq!(still, (15, start));
+        assert_eq!(track_stillness(Some(still), 16, later), (15, start));
+        assert_eq!(track_stillness(Some(still), 14, later), (15, start));
+        assert_eq!(track_stillness(Some(still), 17, later), (17, later));
+    }
+}
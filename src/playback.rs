@@ -0,0 +1,63 @@
+use std::{
+    fs::File,
+    io::{BufReader, LineWriter},
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::{anyhow, Context};
+use osc_ringcon::{
+    joycon,
+    mapping::{MappedOutput, Mapper},
+    osc::OscOut,
+    recording::{self, Recorder},
+};
+
+use crate::config;
+
+/// Reads the Ring-Con until the program is stopped, writing the raw flex
+/// to `path` instead of sending anything.
+pub(crate) fn record(path: &Path) -> anyhow::Result<()> {
+    let file = File::create(path).context("Could not create the recording")?;
+    // Written line by line, since recording only ends when the program is stopped.
+    let mut recorder = Recorder::new(LineWriter::new(file));
+    let config = config::load().unwrap_or_default();
+    println!("Recording to {}, press Ctrl+C to stop", path.display());
+    joycon::run(config, |output: MappedOutput| {
+        if let Err(err) = recorder.record(output.flex, Instant::now()) {
+            eprintln!("Could not record: {}", err);
+        }
+    })
+    .map_err(|e| anyhow!("{:?}", e))
+}
+
+/// Maps a recording made by [`record`] with the current configuration, and
+/// sends the result over OSC as it happened. With `dry_run`, the values are
+/// printed straight away instead.
+pub(crate) fn replay(path: &Path, dry_run: bool) -> anyhow::Result<()> {
+    let file = File::open(path).context("Could not open the recording")?;
+    let readings = recording::read(BufReader::new(file)).context("Could not read the recording")?;
+    let config = config::load().unwrap_or_default();
+    let mut mapper = Mapper::new(&config);
+    let mut osc_out = OscOut::new();
+    osc_out.configure(&config);
+
+    let start = Instant::now();
+    for (at, flex) in readings {
+        let now = start + at;
+        if !dry_run {
+            std::thread::sleep(now.saturating_duration_since(Instant::now()));
+        }
+        mapper.map(flex, now, |output| {
+            if dry_run {
+                println!("{}\t{}\t{}", at.as_millis(), flex, output.value);
+            } else {
+                osc_out.send(&output, now);
+            }
+        });
+    }
+    if !dry_run {
+        osc_out.flush(Instant::now());
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! Recordings of raw flex readings, for trying out mappings without the
+//! Ring-Con.
+//!
+//! A recording is text with one reading per line: the milliseconds since
+//! recording started and the flex, separated by a space.
+
+use std::{
+    io::{self, BufRead, Write},
+    time::{Duration, Instant},
+};
+
+/// Writes readings as they arrive.
+pub struct Recorder<W> {
+    out: W,
+    start: Option<Instant>,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, start: None }
+    }
+
+    /// Writes `flex`, timed from the first reading recorded.
+    pub fn record(&mut self, flex: u8, now: Instant) -> io::Result<()> {
+        let start = *self.start.get_or_insert(now);
+        writeln!(
+            self.out,
+            "{} {}",
+            now.duration_since(start).as_millis(),
+            flex
+        )
+    }
+}
+
+/// Reads a recording made by [`Recorder`], as each reading's time since the
+/// start and flex.
+pub fn read(input: impl BufRead) -> io::Result<Vec<(Duration, u8)>> {
+    let mut readings = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Line {} is not a reading: {}", number + 1, line),
+            )
+        };
+        let (ms, flex) = line.trim().split_once(' ').ok_or_else(invalid)?;
+        let ms: u64 = ms.parse().map_err(|_| invalid())?;
+        let flex: u8 = flex.trim().parse().map_err(|_| invalid())?;
+        readings.push((Duration::from_millis(ms), flex));
+    }
+    Ok(readings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{read, Recorder};
+
+    #[test]
+    fn round_trip() {
+        let start = Instant::now();
+        let mut recorder = Recorder::new(Vec::new());
+        for (ms, flex) in [(0, 15), (16, 17), (1000, 0)] {
+            recorder
+                .record(flex, start + Duration::from_millis(ms))
+                .unwrap();
+        }
+
+        let readings = read(&recorder.out[..]).unwrap();
+        assert_eq!(
+            readings,
+            [
+                (Duration::ZERO, 15),
+                (Duration::from_millis(16), 17),
+                (Duration::from_secs(1), 0)
+            ]
+        );
+        assert!(read(&b"12 300\n"[..]).is_err());
+        assert!(read(&b"15\n"[..]).is_err());
+    }
+}
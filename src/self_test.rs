@@ -0,0 +1,68 @@
+use std::path::Path;
+
+/// Problems with the installation which would otherwise show up as a blank
+/// window or missing text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Problem {
+    /// The `res` folder isn't where it is looked for.
+    MissingResources,
+    /// No translation could be loaded from it.
+    NoTranslations,
+    /// None of the fonts asked for are installed.
+    NoFont,
+}
+
+impl Problem {
+    /// Describes the problem. Not translated, since the translations may be
+    /// what is missing.
+    pub fn message(self) -> &'static str {
+        match self {
+            Problem::MissingResources => {
+                "The res folder is missing. Start osc-ringcon.exe from the folder it was extracted to, with the res folder next to it."
+            }
+            Problem::NoTranslations => {
+                "The text in the res folder couldn't be loaded. Extract the program again."
+            }
+            Problem::NoFont => {
+                "None of the fonts for your language are installed, so some text may not show. Choose another font in the settings."
+            }
+        }
+    }
+}
+
+/// Checks what was loaded at startup. `title` is the translated window
+/// title, which is empty or just its id if no translation loaded.
+pub(crate) fn check(res_dir: &Path, title: &str, font_found: bool) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if !res_dir.is_dir() {
+        problems.push(Problem::MissingResources);
+    } else if title.is_empty() || title == "title" {
+        problems.push(Problem::NoTranslations);
+    }
+    if !font_found {
+        problems.push(Problem::NoFont);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{check, Problem};
+
+    #[test]
+    fn missing_folder_hides_missing_translations() {
+        let missing = Path::new("does-not-exist");
+        assert_eq!(check(missing, "", true), [Problem::MissingResources]);
+        assert_eq!(
+            check(Path::new("res"), "", false),
+            [Problem::NoTranslations, Problem::NoFont]
+        );
+        assert_eq!(
+            check(Path::new("res"), "title", true),
+            [Problem::NoTranslations]
+        );
+        assert!(check(Path::new("res"), "OSC Ring-Con", true).is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use std::{ffi::OsString, path::PathBuf, sync::OnceLock, time::Duration};
+
+use anyhow::{anyhow, Context};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+    service_dispatcher,
+};
+
+use crate::{agent, config};
+
+/// Name the service has to be installed under.
+const SERVICE_NAME: &str = "osc-ringcon";
+
+/// Configuration file given on the command line. The service entry point
+/// can't take it as an argument.
+static CONFIG_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Runs the agent without the window, for when Windows starts this program
+/// as a service. The configuration is read from `config_path`, or from the
+/// usual place.
+pub(crate) fn run(config_path: Option<PathBuf>) -> anyhow::Result<()> {
+    CONFIG_PATH.set(config_path).unwrap();
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+        .context("Could not start the service dispatcher")
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(err) = run_service() {
+        eprintln!("Service failed: {:#}", err);
+    }
+}
+
+fn run_service() -> anyhow::Result<()> {
+    let (stop_tx, stop_rx) = std::sync::mpsc::channel();
+    let status_handle =
+        service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                let _ = stop_tx.send(());
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })
+        .context("Could not register the service control handler")?;
+
+    let config = match CONFIG_PATH.get().and_then(Option::as_ref) {
+        Some(path) => config::load_from(path)
+            .ok_or_else(|| anyhow!("Could not read configuration {}", path.display()))?,
+        None => config::load().unwrap_or_default(),
+    };
+
+    set_state(
+        &status_handle,
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+    )?;
+
+    let runtime = tokio::runtime::Runtime::new().context("Could not start the runtime")?;
+    runtime.block_on(async {
+        // Nothing reads the status, but the agent is restarted if the
+        // receiver goes away.
+        let (mut config_tx, _status_rx, _osc_in_rx) = agent::spawn(false);
+        config_tx
+            .try_send(config.clone())
+            .context("Could not send configuration")?;
+        tokio::task::spawn_blocking(move || stop_rx.recv())
+            .await
+            .context("Waiting for stop panicked")?
+            .context("Service control handler went away")
+    })?;
+    runtime.shutdown_background();
+
+    // The agent stops once this process exits, without resetting the avatar.
+    agent::send_idle(&config);
+    set_state(
+        &status_handle,
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+    )
+}
+
+fn set_state(
+    status_handle: &ServiceStatusHandle,
+    state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+) -> anyhow::Result<()> {
+    status_handle
+        .set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        })
+        .context("Could not report the service status")
+}
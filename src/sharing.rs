@@ -0,0 +1,119 @@
+use anyhow::{anyhow, Context};
+
+use osc_ringcon::messages::Configuration;
+
+/// Marks copied settings, so that pasting something else fails clearly.
+const PREFIX: &str = "osc-ringcon:";
+
+/// Encodes the settings as one line of text which can be pasted into chat.
+/// The shared secret is left out.
+pub(crate) fn export(config: &Configuration) -> String {
+    let config = Configuration {
+        shared_secret: None,
+        ..config.clone()
+    };
+    format!("{}{}", PREFIX, serde_json::to_string(&config).unwrap())
+}
+
+/// Decodes settings copied with [`export`]. Settings which only make sense
+/// on this computer, like where to send to and how the window looks, are
+/// kept from `current`.
+pub(crate) fn import(text: &str, current: &Configuration) -> anyhow::Result<Configuration> {
+    let json = text
+        .trim()
+        .strip_prefix(PREFIX)
+        .ok_or_else(|| anyhow!("Not osc-ringcon settings"))?;
+    let shared: Configuration = serde_json::from_str(json).context("Invalid settings")?;
+
+    let range = &shared.in_range;
+    // The center has to stay strictly inside the range for the mapping to work.
+    if !(*range.start() < shared.in_center && shared.in_center < *range.end()) {
+        return Err(anyhow!(
+            "Center {} is not inside {}..={}",
+            shared.in_center,
+            range.start(),
+            range.end()
+        ));
+    }
+
+    Ok(Configuration {
+        udp_address: current.udp_address,
+        multicast_ttl: current.multicast_ttl,
+        discover_port: current.discover_port,
+        osc_in_port: current.osc_in_port,
+        preferred_serial: current.preferred_serial.clone(),
+        auto_start: current.auto_start,
+        live_apply: current.live_apply,
+        font_family: current.font_family.clone(),
+        ui_scale: current.ui_scale,
+        display_unit: current.display_unit,
+        mini_mode: current.mini_mode,
+        low_power_ui: current.low_power_ui,
+        audio_cues: current.audio_cues,
+        metrics_port: current.metrics_port,
+        shared_secret: current.shared_secret.clone(),
+        log_packets: current.log_packets,
+        agent_linger_s: current.agent_linger_s,
+        orphaned: current.orphaned,
+        window_size: current.window_size,
+        ..shared
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+    use osc_ringcon::messages::Configuration;
+
+    use super::{export, import};
+
+    #[test]
+    fn round_trip_keeps_local_settings() {
+        let shared = Configuration {
+            in_range: 5..=30,
+            in_center: 16,
+            osc_address: "/avatar/parameters/grip".to_string(),
+            ui_scale: 2.0,
+            ..Configuration::default()
+        };
+        let current = Configuration {
+            udp_address: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 168, 0, 2), 9000)),
+            ..Configuration::default()
+        };
+
+        let imported = import(&export(&shared), &current).unwrap();
+        assert_eq!(imported.in_range, 5..=30);
+        assert_eq!(imported.in_center, 16);
+        assert_eq!(imported.osc_address, "/avatar/parameters/grip");
+        assert_eq!(imported.ui_scale, current.ui_scale);
+        assert_eq!(imported.udp_address, current.udp_address);
+    }
+
+    #[test]
+    fn secret_is_not_exported() {
+        let config = Configuration {
+            shared_secret: Some("hunter2".to_string()),
+            ..Configuration::default()
+        };
+        assert!(!export(&config).contains("hunter2"));
+        let imported = import(&export(&Configuration::default()), &config).unwrap();
+        assert_eq!(imported.shared_secret, config.shared_secret);
+    }
+
+    #[test]
+    fn rejects_other_text() {
+        assert!(import("hello", &Configuration::default()).is_err());
+        assert!(import("osc-ringcon:{}", &Configuration::default()).is_err());
+    }
+
+    #[test]
+    fn rejects_center_outside_range() {
+        let shared = Configuration {
+            in_range: 5..=30,
+            in_center: 30,
+            ..Configuration::default()
+        };
+        assert!(import(&export(&shared), &Configuration::default()).is_err());
+    }
+}
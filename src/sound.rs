@@ -0,0 +1,95 @@
+use std::thread;
+
+use osc_ringcon::messages::Status;
+use windows::Win32::System::Diagnostics::Debug::Beep;
+
+/// Short tones for when something happens that is otherwise only shown in
+/// the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Cue {
+    /// The Ring-Con started being read.
+    Connected,
+    /// The Joy-Con was lost.
+    Disconnected,
+    /// The input range was replaced with the observed one.
+    Calibrated,
+}
+
+impl Cue {
+    /// The cue for a status change, if it deserves one.
+    pub fn for_transition(previous: &Status, status: &Status) -> Option<Cue> {
+        match (previous, status) {
+            (Status::Active(_), Status::Active(_)) => None,
+            (_, Status::Active(_)) => Some(Cue::Connected),
+            (Status::Disconnected, Status::Disconnected) => None,
+            (_, Status::Disconnected) => Some(Cue::Disconnected),
+            _ => None,
+        }
+    }
+
+    /// Plays the cue without waiting for it to finish.
+    pub fn play(self) {
+        // Frequencies in Hz and durations in milliseconds.
+        let tones: &'static [(u32, u32)] = match self {
+            Cue::Connected => &[(660, 100), (880, 150)],
+            Cue::Disconnected => &[(880, 100), (440, 200)],
+            Cue::Calibrated => &[(1000, 80)],
+        };
+        // Beep blocks until the tone is over.
+        thread::spawn(move || {
+            for &(frequency, duration) in tones {
+                unsafe {
+                    Beep(frequency, duration);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_ringcon::messages::{Battery, InitializationStep, Reading, Status};
+    use osc_ringcon::speed::SpeedCategory;
+
+    use super::Cue;
+
+    fn active() -> Status {
+        Status::Active(Reading {
+            flex: 15,
+            observed: 10..=20,
+            value: 0.75,
+            speed: SpeedCategory::Still,
+            battery: Battery {
+                percent: 100,
+                charging: false,
+            },
+            calibration_needed: false,
+        })
+    }
+
+    #[test]
+    fn transitions() {
+        let initializing = Status::Initializing(InitializationStep::Step7);
+        assert_eq!(
+            Cue::for_transition(&initializing, &active()),
+            Some(Cue::Connected)
+        );
+        assert_eq!(
+            Cue::for_transition(&Status::NoRingCon, &active()),
+            Some(Cue::Connected)
+        );
+        assert_eq!(Cue::for_transition(&active(), &active()), None);
+        assert_eq!(
+            Cue::for_transition(&active(), &Status::Disconnected),
+            Some(Cue::Disconnected)
+        );
+        assert_eq!(
+            Cue::for_transition(&Status::Disconnected, &Status::Disconnected),
+            None
+        );
+        assert_eq!(
+            Cue::for_transition(&Status::NotConnected, &Status::NoRingCon),
+            None
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use iced::canvas::{Cursor, Frame, Geometry, Path, Program, Stroke};
+use iced::{Color, Element, Length, Point, Rectangle};
+
+use crate::canvas;
+
+/// How far back the sparkline goes.
+const WINDOW: Duration = Duration::from_secs(5);
+
+/// Recent flex readings, with gaps where the Ring-Con was missing.
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    samples: VecDeque<(Instant, Option<u8>)>,
+}
+
+impl History {
+    /// Records a reading, or a gap if `flex` is `None`.
+    pub fn push(&mut self, flex: Option<u8>, now: Instant) {
+        // Only the first gap matters for drawing.
+        if flex.is_none() && matches!(self.samples.back(), Some((_, None))) {
+            return;
+        }
+        self.samples.push_back((now, flex));
+        while let Some((time, _)) = self.samples.front() {
+            if now.duration_since(*time) <= WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn view<'a, Message: 'static>(
+        &'a self,
+        range: RangeInclusive<u8>,
+        color: Color,
+    ) -> Element<'a, Message> {
+        canvas::element(
+            Sparkline {
+                history: self,
+                range,
+                color,
+            },
+            Length::Fill,
+            Length::Units(40),
+        )
+    }
+}
+
+struct Sparkline<'a> {
+    history: &'a History,
+    /// Readings at the ends of this range are drawn at the bottom and top.
+    range: RangeInclusive<u8>,
+    color: Color,
+}
+
+impl<Message> Program<Message> for Sparkline<'_> {
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let now = Instant::now();
+        let low = f32::from(*self.range.start());
+        let span = f32::from(self.range.end().saturating_sub(*self.range.start())).max(1.0);
+
+        let path = Path::new(|builder| {
+            let mut drawing = false;
+            for (time, flex) in &self.history.samples {
+                let flex = match flex {
+                    Some(flex) => f32::from(*flex),
+                    None => {
+                        drawing = false;
+                        continue;
+                    }
+                };
+                let age = now.duration_since(*time).as_secs_f32() / WINDOW.as_secs_f32();
+                let x = frame.width() * (1.0 - age.min(1.0));
+                let level = ((flex - low) / span).clamp(0.0, 1.0);
+                let point = Point::new(x, frame.height() * (1.0 - level));
+                if drawing {
+                    builder.line_to(point);
+                } else {
+                    builder.move_to(point);
+                    drawing = true;
+                }
+            }
+        });
+        frame.stroke(
+            &path,
+            Stroke::default().with_color(self.color).with_width(2.0),
+        );
+
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_old_samples() {
+        let start = Instant::now();
+        let mut history = History::default();
+        history.push(Some(10), start);
+        history.push(Some(20), start + Duration::from_secs(3));
+        history.push(Some(30), start + Duration::from_secs(6));
+        let flex: Vec<_> = history.samples.iter().map(|(_, flex)| *flex).collect();
+        assert_eq!(flex, [Some(20), Some(30)]);
+    }
+
+    #[test]
+    fn keeps_one_gap() {
+        let start = Instant::now();
+        let mut history = History::default();
+        history.push(Some(10), start);
+        history.push(None, start);
+        history.push(None, start);
+        assert_eq!(history.samples.len(), 2);
+    }
+}
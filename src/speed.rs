@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// How much the speed has to fall below a threshold before dropping back to
+/// the slower category, so that it doesn't flicker at the boundary.
+const HYSTERESIS: f32 = 0.8;
+/// Time constant for smoothing the speed, since single readings only move in
+/// whole steps.
+const SMOOTHING: Duration = Duration::from_millis(250);
+
+/// How fast the Ring-Con is being flexed, sent as an int parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeedCategory {
+    Still = 0,
+    Slow = 1,
+    Fast = 2,
+}
+
+/// Sorts the rate of change of the flex reading into [`SpeedCategory`]s.
+pub struct SpeedTracker {
+    /// Flex steps per second at which `Slow` and `Fast` start.
+    thresholds: (f32, f32),
+    last: Option<(u8, Instant)>,
+    speed: f32,
+    category: SpeedCategory,
+}
+
+impl SpeedTracker {
+    pub fn new(thresholds: (f32, f32)) -> Self {
+        Self {
+            thresholds,
+            last: None,
+            speed: 0.0,
+            category: SpeedCategory::Still,
+        }
+    }
+
+    pub fn set_thresholds(&mut self, thresholds: (f32, f32)) {
+        self.thresholds = thresholds;
+    }
+
+    pub fn update(&mut self, flex: u8, now: Instant) -> SpeedCategory {
+        if flex == 0 {
+            *self = Self::new(self.thresholds);
+            return self.category;
+        }
+
+        if let Some((prev, since)) = self.last {
+            let elapsed = now.duration_since(since).as_secs_f32();
+            if elapsed > 0.0 {
+                let speed = f32::from(flex.abs_diff(prev)) / elapsed;
+                let weight = (elapsed / SMOOTHING.as_secs_f32()).min(1.0);
+                self.speed += (speed - self.speed) * weight;
+            }
+        }
+        self.last = Some((flex, now));
+
+        let (slow, fast) = self.thresholds;
+        let bound = |threshold: f32, current: bool| {
+            if current {
+                threshold * HYSTERESIS
+            } else {
+                threshold
+            }
+        };
+        self.category = if self.speed >= bound(fast, self.category == SpeedCategory::Fast) {
+            SpeedCategory::Fast
+        } else if self.speed >= bound(slow, self.category != SpeedCategory::Still) {
+            SpeedCategory::Slow
+        } else {
+            SpeedCategory::Still
+        };
+        self.category
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{SpeedCategory, SpeedTracker};
+
+    fn run(tracker: &mut SpeedTracker, start: Instant, readings: &[(u64, u8)]) -> SpeedCategory {
+        let mut category = SpeedCategory::Still;
+        for &(ms, flex) in readings {
+            category = tracker.update(flex, start + Duration::from_millis(ms));
+        }
+        category
+    }
+
+    #[test]
+    fn categorizes_speed() {
+        let start = Instant::now();
+
+        let mut tracker = SpeedTracker::new((5.0, 30.0));
+        assert_eq!(
+            run(&mut tracker, start, &[(0, 15), (1000, 15)]),
+            SpeedCategory::Still
+        );
+
+        let mut tracker = SpeedTracker::new((5.0, 30.0));
+        assert_eq!(
+            run(&mut tracker, start, &[(0, 15), (500, 20)]),
+            SpeedCategory::Slow
+        );
+
+        let mut tracker = SpeedTracker::new((5.0, 30.0));
+        assert_eq!(
+            run(&mut tracker, start, &[(0, 10), (250, 20)]),
+            SpeedCategory::Fast
+        );
+    }
+
+    #[test]
+    fn hysteresis() {
+        let start = Instant::now();
+        let mut tracker = SpeedTracker::new((10.0, 100.0));
+        // 12 steps per second.
+        assert_eq!(
+            run(&mut tracker, start, &[(0, 10), (250, 13)]),
+            SpeedCategory::Slow
+        );
+        // Settles at 9 steps per second, below the threshold but within the margin.
+        assert_eq!(run(&mut tracker, start, &[(1250, 22)]), SpeedCategory::Slow);
+        // 7 steps per second is far enough below.
+        assert_eq!(
+            run(&mut tracker, start, &[(2250, 15)]),
+            SpeedCategory::Still
+        );
+    }
+
+    #[test]
+    fn reset_by_idle() {
+        let start = Instant::now();
+        let mut tracker = SpeedTracker::new((5.0, 30.0));
+        assert_eq!(
+            run(&mut tracker, start, &[(0, 10), (250, 20)]),
+            SpeedCategory::Fast
+        );
+        assert_eq!(run(&mut tracker, start, &[(300, 0)]), SpeedCategory::Still);
+        assert_eq!(run(&mut tracker, start, &[(350, 20)]), SpeedCategory::Still);
+    }
+}
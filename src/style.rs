@@ -0,0 +1,57 @@
+use iced::{button, progress_bar, Background, Color};
+
+use osc_ringcon::messages::Status;
+
+/// How healthy the connection is, for coloring the UI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Tone {
+    Waiting,
+    Good,
+    Bad,
+}
+
+impl Tone {
+    pub fn of(status: &Status) -> Self {
+        match status {
+            Status::NotConnected
+            | Status::Initializing(_)
+            | Status::NoRingCon
+            | Status::MultipleDevices(_) => Tone::Waiting,
+            Status::Active(..) => Tone::Good,
+            Status::Disconnected
+            | Status::UnsupportedDevice(_)
+            | Status::HardwareError(_)
+            | Status::UnsupportedFirmware => Tone::Bad,
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Tone::Waiting => Color::from_rgb8(0xc7, 0x7c, 0x02),
+            Tone::Good => Color::from_rgb8(0x2e, 0x8b, 0x3e),
+            Tone::Bad => Color::from_rgb8(0xc6, 0x28, 0x28),
+        }
+    }
+}
+
+impl progress_bar::StyleSheet for Tone {
+    fn style(&self) -> progress_bar::Style {
+        progress_bar::Style {
+            background: Background::Color(Color::from_rgb(0.6, 0.6, 0.6)),
+            bar: Background::Color(self.color()),
+            border_radius: 5.0,
+        }
+    }
+}
+
+/// A filled button in the tone's color, for actions which should stand out.
+impl button::StyleSheet for Tone {
+    fn active(&self) -> button::Style {
+        button::Style {
+            background: Some(Background::Color(self.color())),
+            border_radius: 2.0,
+            text_color: Color::WHITE,
+            ..button::Style::default()
+        }
+    }
+}
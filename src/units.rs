@@ -0,0 +1,82 @@
+use std::ops::RangeInclusive;
+
+use osc_ringcon::messages::DisplayUnit;
+
+/// Formats an output value, which is stored as a fraction.
+pub(crate) fn format_output(value: f32, unit: DisplayUnit) -> String {
+    match unit {
+        DisplayUnit::Raw => format!("{}", value),
+        DisplayUnit::Percent => format!("{}%", (value * 100.0).round()),
+    }
+}
+
+/// Formats a raw flex reading, as a percentage of `observed` if asked to and
+/// there is a range to compare against.
+pub(crate) fn format_input(
+    flex: u8,
+    unit: DisplayUnit,
+    observed: Option<&RangeInclusive<u8>>,
+) -> String {
+    match (unit, observed) {
+        (DisplayUnit::Percent, Some(observed)) if observed.start() < observed.end() => {
+            let span = f32::from(observed.end() - observed.start());
+            let percent = (f32::from(flex) - f32::from(*observed.start())) / span * 100.0;
+            format!("{}%", percent.round())
+        }
+        _ => flex.to_string(),
+    }
+}
+
+pub(crate) fn format_output_range(range: &RangeInclusive<f32>, unit: DisplayUnit) -> String {
+    format!(
+        "{}–{}",
+        format_output(*range.start(), unit),
+        format_output(*range.end(), unit)
+    )
+}
+
+pub(crate) fn format_input_range(
+    range: &RangeInclusive<u8>,
+    unit: DisplayUnit,
+    observed: Option<&RangeInclusive<u8>>,
+) -> String {
+    format!(
+        "{}–{}",
+        format_input(*range.start(), unit, observed),
+        format_input(*range.end(), unit, observed)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_ringcon::messages::DisplayUnit;
+
+    use super::{format_input_range, format_output_range};
+
+    #[test]
+    fn output_ranges() {
+        assert_eq!(format_output_range(&(0.5..=1.0), DisplayUnit::Raw), "0.5–1");
+        assert_eq!(
+            format_output_range(&(0.5..=1.0), DisplayUnit::Percent),
+            "50%–100%"
+        );
+    }
+
+    #[test]
+    fn input_ranges() {
+        let observed = 4..=24;
+        assert_eq!(
+            format_input_range(&(7..=24), DisplayUnit::Raw, Some(&observed)),
+            "7–24"
+        );
+        assert_eq!(
+            format_input_range(&(9..=24), DisplayUnit::Percent, Some(&observed)),
+            "25%–100%"
+        );
+        // Without an observed range there is nothing to take a percentage of.
+        assert_eq!(
+            format_input_range(&(7..=24), DisplayUnit::Percent, None),
+            "7–24"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use osc_ringcon::messages::Status;
+
+/// Steps of the setup shown on the first start, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// Connect the Joy-Con and put it in the Ring-Con.
+    Connect,
+    /// Find where VRChat listens and check that it can be reached.
+    Port,
+    /// Flex the Ring-Con both ways to find its range.
+    Calibrate,
+}
+
+impl Step {
+    pub(crate) fn next(self) -> Option<Step> {
+        match self {
+            Step::Connect => Some(Step::Port),
+            Step::Port => Some(Step::Calibrate),
+            Step::Calibrate => None,
+        }
+    }
+
+    /// Whether the step is done, given the agent's status and whether a
+    /// usable range has been observed.
+    pub(crate) fn is_done(self, status: &Status, calibrated: bool) -> bool {
+        match self {
+            Step::Connect => matches!(status, Status::Active(..)),
+            Step::Port => true,
+            Step::Calibrate => calibrated,
+        }
+    }
+
+    pub(crate) fn help_key(self) -> &'static str {
+        match self {
+            Step::Connect => "wizard-connect",
+            Step::Port => "wizard-port",
+            Step::Calibrate => "wizard-calibrate",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_ringcon::messages::Status;
+
+    use super::Step;
+
+    #[test]
+    fn steps_wait_for_what_they_need() {
+        let mut step = Step::Connect;
+        assert!(!step.is_done(&Status::NoRingCon, true));
+        step = step.next().unwrap();
+        assert!(step.is_done(&Status::NotConnected, false));
+        step = step.next().unwrap();
+        assert_eq!(step, Step::Calibrate);
+        assert!(!step.is_done(&Status::NoRingCon, false));
+        assert!(step.is_done(&Status::NoRingCon, true));
+        assert_eq!(step.next(), None);
+    }
+}
@@ -0,0 +1,150 @@
+//! Feeds readings through the mapping and sends them over a real UDP socket
+//! to a receiver, without a Joy-Con.
+
+use std::{
+    net::{Ipv4Addr, SocketAddrV4, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use osc_ringcon::{mapping::Mapper, messages::Configuration, osc::OscOut};
+
+/// An OSC message with one argument, as far as this program sends them.
+#[derive(Debug, PartialEq)]
+enum Received {
+    Float(String, f32),
+    Int(String, i32),
+    Bool(String, bool),
+}
+
+/// Reads a null terminated string padded to 4 bytes, returning it and the rest.
+fn read_string(packet: &[u8]) -> (String, &[u8]) {
+    let end = packet
+        .iter()
+        .position(|&b| b == 0)
+        .expect("unterminated string");
+    assert!(
+        packet[end..(end + 4) & !3].iter().all(|&b| b == 0),
+        "bad padding in {:?}",
+        packet
+    );
+    let string = String::from_utf8(packet[..end].to_vec()).unwrap();
+    (string, &packet[(end + 4) & !3..])
+}
+
+fn decode(packet: &[u8]) -> Received {
+    assert_eq!(packet.len() % 4, 0, "unaligned packet {:?}", packet);
+    let (address, rest) = read_string(packet);
+    let (type_tags, rest) = read_string(rest);
+    let argument = |rest: &[u8]| <[u8; 4]>::try_from(rest).expect("one argument");
+    match type_tags.as_str() {
+        ",f" => Received::Float(address, f32::from_be_bytes(argument(rest))),
+        ",i" => Received::Int(address, i32::from_be_bytes(argument(rest))),
+        ",T" | ",F" => {
+            assert!(rest.is_empty(), "bool with an argument {:?}", packet);
+            Received::Bool(address, type_tags == ",T")
+        }
+        _ => panic!("unexpected type tags {:?}", type_tags),
+    }
+}
+
+fn receive_all(receiver: &UdpSocket) -> Vec<Received> {
+    let mut received = Vec::new();
+    let mut buffer = [0; 1024];
+    while let Ok(len) = receiver.recv(&mut buffer) {
+        received.push(decode(&buffer[..len]));
+    }
+    received
+}
+
+#[test]
+fn readings_arrive_as_osc() {
+    let receiver = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+    receiver
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+
+    let config = Configuration {
+        udp_address: receiver.local_addr().unwrap(),
+        discover_port: false,
+        osc_address: "/avatar/parameters/ringcon_flex".to_string(),
+        speed_category_address: Some("/speed".to_string()),
+        clench_address: Some("/clench".to_string()),
+        // Readings are sent as fast as they are mapped here.
+        min_packet_interval_ms: 0,
+        ..Configuration::default()
+    };
+    let transport = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let mut osc_out = OscOut::with_transport(transport);
+    osc_out.configure(&config);
+    let mut mapper = Mapper::new(&config);
+
+    // Far enough apart that speed and squeeze detection stay quiet.
+    let start = Instant::now();
+    for (i, flex) in [0, 15, 7, 24, 255, 0].into_iter().enumerate() {
+        let now = start + Duration::from_secs(i as u64 * 100);
+        mapper.map(flex, now, |output| osc_out.send(&output, now));
+    }
+
+    let flex = "/avatar/parameters/ringcon_flex".to_string();
+    let reading = |value: f32| {
+        [
+            Received::Float(flex.clone(), value),
+            Received::Int("/speed".to_string(), 0),
+            Received::Bool("/clench".to_string(), false),
+        ]
+    };
+    let pulled = 0.75 + 8.0 * (0.25 / 9.0);
+    let expected: Vec<_> = [
+        reading(0.0),
+        reading(0.75),
+        reading(pulled),
+        reading(0.5),
+        reading(0.5),
+        reading(0.0),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    assert_eq!(receive_all(&receiver), expected);
+}
+
+#[test]
+fn complementary_pair_replaces_the_flex() {
+    let receiver = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+    receiver
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+
+    let config = Configuration {
+        udp_address: receiver.local_addr().unwrap(),
+        discover_port: false,
+        osc_address: "/avatar/parameters/ringcon_flex".to_string(),
+        complementary: Some(("/smile".to_string(), "/frown".to_string())),
+        // Readings are sent as fast as they are mapped here.
+        min_packet_interval_ms: 0,
+        ..Configuration::default()
+    };
+    let transport = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let mut osc_out = OscOut::with_transport(transport);
+    osc_out.configure(&config);
+    let mut mapper = Mapper::new(&config);
+
+    let start = Instant::now();
+    for (i, flex) in [15, 24, 0].into_iter().enumerate() {
+        let now = start + Duration::from_secs(i as u64 * 100);
+        mapper.map(flex, now, |output| osc_out.send(&output, now));
+    }
+
+    let pair = |value: f32, complement: f32| {
+        [
+            Received::Float("/smile".to_string(), value),
+            Received::Float("/frown".to_string(), complement),
+        ]
+    };
+    // The idle value isn't complemented.
+    let expected: Vec<_> = [pair(0.75, 0.25), pair(0.5, 0.5), pair(0.0, 0.0)]
+        .into_iter()
+        .flatten()
+        .collect();
+    assert_eq!(receive_all(&receiver), expected);
+}